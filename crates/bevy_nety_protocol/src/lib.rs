@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 pub type NetworkConnector = Box<dyn NetworkConnectorProtocol + Send + Sync>;
 pub type NetworkHost = Box<dyn NetworkHostProtocol + Send + Sync>;
 pub type NetworkSocket = Box<dyn NetworkSocketProtocol + Send + Sync>;
@@ -27,6 +29,11 @@ pub trait NetworkSocketProtocol {
     fn update(&mut self);
     fn connected(&mut self) -> bool;
     fn send(&mut self, message: String);
+    // the same message sent to many sockets is encoded once and shared between them,
+    // sockets that can write a borrowed message should override this to skip the copy
+    fn send_shared(&mut self, message: Arc<str>) {
+        self.send(message.to_string());
+    }
     fn receive(&mut self) -> Option<String>;
     fn disconnect(&mut self);
 }
@@ -5,6 +5,7 @@ use bevy_nety_protocol::{
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Cursor, ErrorKind, Read, Result, Write};
 use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
 
 // TODO: replace with a threaded/blocking implementation
 // TODO: remove panic inducing unwraps
@@ -77,6 +78,18 @@ pub struct TcpSocket {
     connected: bool,
 }
 
+impl TcpSocket {
+    fn write(&mut self, message: &str) {
+        self.write_buffer
+            .write_u16::<LittleEndian>(message.len() as u16)
+            .unwrap();
+        self.write_buffer.write_all(message.as_bytes()).unwrap();
+        if let Ok(len) = self.stream.write(&self.write_buffer) {
+            self.write_buffer.drain(0..len);
+        }
+    }
+}
+
 impl NetworkSocketProtocol for TcpSocket {
     fn update(&mut self) {
         let mut buf: [u8; 16384] = [0; 16384];
@@ -97,13 +110,10 @@ impl NetworkSocketProtocol for TcpSocket {
         self.connected
     }
     fn send(&mut self, message: String) {
-        self.write_buffer
-            .write_u16::<LittleEndian>(message.len() as u16)
-            .unwrap();
-        self.write_buffer.write_all(message.as_bytes()).unwrap();
-        if let Ok(len) = self.stream.write(&self.write_buffer) {
-            self.write_buffer.drain(0..len);
-        }
+        self.write(&message);
+    }
+    fn send_shared(&mut self, message: Arc<str>) {
+        self.write(&message);
     }
     fn receive(&mut self) -> Option<String> {
         if self.read_buffer.len() >= 2 {
@@ -1,9 +1,24 @@
 use crate::{
     events::{NetworkEntityEvent, NetworkEvent, NetworkEventTraits, NetworkServerEvent},
+    input::{NetworkInputTraits, NetworkInputs},
+    lag_compensation::NetworkLagCompensationTraits,
+    lockstep::{NetworkLockstepTickEvent, NetworkLockstepTraits},
     network::Network,
+    player::NetworkPlayer,
     player_data::NetworkPlayerDataTraits,
+    prefab::NetworkPrefabTraits,
+    relevancy::{NetworkRelevancyRule, NetworkRelevancyRuleMode},
+    resource::NetworkResourceTraits,
+    rollback::{
+        NetworkRollbackComponentTraits, NetworkRollbackInputTraits, NetworkRollbackInputs,
+        NetworkRollbackResourceTraits, NetworkRollbackStage,
+    },
+    state::{guard_client_state, NetworkStateRequestEvent, NetworkStateTraits},
+};
+use bevy::{
+    ecs::{schedule::IntoSystemDescriptor, world::EntityMut},
+    prelude::*,
 };
-use bevy::prelude::*;
 
 const ERROR_MESSAGE: &str = "Can't register network event, please add the NetworkPlugin";
 
@@ -19,6 +34,63 @@ pub trait AddNetworkData {
     fn add_network_player_data<T>(&mut self) -> &mut Self
     where
         T: NetworkPlayerDataTraits;
+
+    fn add_network_lag_compensation<T>(&mut self) -> &mut Self
+    where
+        T: NetworkLagCompensationTraits;
+
+    fn add_network_input<T>(&mut self) -> &mut Self
+    where
+        T: NetworkInputTraits;
+
+    fn add_network_lockstep<T>(&mut self) -> &mut Self
+    where
+        T: NetworkLockstepTraits;
+
+    fn add_network_rollback<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackInputTraits;
+
+    fn add_network_rollback_component<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackComponentTraits;
+
+    fn add_network_rollback_resource<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackResourceTraits;
+
+    fn add_network_rollback_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+
+    fn add_network_prefab<T, F>(&mut self, builder: F) -> &mut Self
+    where
+        T: NetworkPrefabTraits,
+        F: Fn(&mut EntityMut, &T) + Send + Sync + 'static;
+
+    fn add_network_resource<T>(&mut self) -> &mut Self
+    where
+        T: NetworkResourceTraits;
+
+    // only players the filter returns true for get the resource
+    fn add_network_resource_filtered<T, F>(&mut self, filter: F) -> &mut Self
+    where
+        T: NetworkResourceTraits,
+        F: Fn(NetworkPlayer, &T) -> bool + Send + Sync + 'static;
+
+    // the server drives the state, clients follow it and send requests when they change it
+    fn add_network_state<S>(&mut self) -> &mut Self
+    where
+        S: NetworkStateTraits;
+
+    fn add_network_relevancy_rule<R>(
+        &mut self,
+        mode: NetworkRelevancyRuleMode,
+        rule: R,
+    ) -> &mut Self
+    where
+        R: NetworkRelevancyRule;
 }
 
 impl AddNetworkData for App {
@@ -60,4 +132,168 @@ impl AddNetworkData for App {
         network.registry.add_network_player_data::<T>();
         self
     }
+
+    fn add_network_lag_compensation<T>(&mut self) -> &mut Self
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_lag_compensation::<T>();
+        self
+    }
+
+    fn add_network_input<T>(&mut self) -> &mut Self
+    where
+        T: NetworkInputTraits,
+    {
+        self.init_resource::<NetworkInputs<T>>();
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_input::<T>();
+        self
+    }
+
+    fn add_network_lockstep<T>(&mut self) -> &mut Self
+    where
+        T: NetworkLockstepTraits,
+    {
+        self.add_event::<NetworkLockstepTickEvent<T>>();
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_lockstep::<T>();
+        self
+    }
+
+    fn add_network_rollback<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackInputTraits,
+    {
+        self.init_resource::<NetworkRollbackInputs<T>>();
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_rollback::<T>();
+        self
+    }
+
+    fn add_network_rollback_component<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackComponentTraits,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_rollback_component::<T>();
+        self
+    }
+
+    fn add_network_rollback_resource<T>(&mut self) -> &mut Self
+    where
+        T: NetworkRollbackResourceTraits,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_rollback_resource::<T>();
+        self
+    }
+
+    fn add_network_rollback_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        let mut stage = self
+            .world
+            .get_resource_mut::<NetworkRollbackStage>()
+            .expect(ERROR_MESSAGE);
+        stage.0.add_system(system);
+        self
+    }
+
+    fn add_network_prefab<T, F>(&mut self, builder: F) -> &mut Self
+    where
+        T: NetworkPrefabTraits,
+        F: Fn(&mut EntityMut, &T) + Send + Sync + 'static,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_prefab::<T, F>(builder);
+        self
+    }
+
+    fn add_network_resource<T>(&mut self) -> &mut Self
+    where
+        T: NetworkResourceTraits,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_resource::<T>();
+        self
+    }
+
+    fn add_network_resource_filtered<T, F>(&mut self, filter: F) -> &mut Self
+    where
+        T: NetworkResourceTraits,
+        F: Fn(NetworkPlayer, &T) -> bool + Send + Sync + 'static,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network
+            .registry
+            .add_network_resource_filtered::<T, F>(filter);
+        self
+    }
+
+    fn add_network_state<S>(&mut self) -> &mut Self
+    where
+        S: NetworkStateTraits,
+    {
+        self.add_event::<NetworkStateRequestEvent<S>>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                guard_client_state::<S>.exclusive_system().at_end(),
+            )
+            .add_system_to_stage(
+                CoreStage::Update,
+                guard_client_state::<S>.exclusive_system().at_end(),
+            );
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_state::<S>();
+        self
+    }
+
+    fn add_network_relevancy_rule<R>(
+        &mut self,
+        mode: NetworkRelevancyRuleMode,
+        rule: R,
+    ) -> &mut Self
+    where
+        R: NetworkRelevancyRule,
+    {
+        let mut network = self
+            .world
+            .get_resource_mut::<Network>()
+            .expect(ERROR_MESSAGE);
+        network.registry.add_network_relevancy_rule(mode, rule);
+        self
+    }
 }
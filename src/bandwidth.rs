@@ -0,0 +1,208 @@
+use crate::entity::NetworkEntity;
+use bevy::prelude::*;
+use bevy_nety_protocol::NetworkSocket;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+// updates waiting on a budget or update rate past this are dropped, oldest first
+pub(crate) const MAX_PENDING_UPDATES: usize = 256;
+
+// how much an entity's updates are favored when a player's bandwidth budget runs out,
+// entities without one have a priority of 1
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+pub struct NetworkPriority(pub f32);
+
+impl Default for NetworkPriority {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+// counted since the player's budget was last set
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct NetworkBandwidthStats {
+    pub ticks: u64,
+    // ticks that ended with updates left for later ticks
+    pub saturated_ticks: u64,
+    pub sent_bytes: u64,
+    // every tick an update waits counts once
+    pub deferred_updates: u64,
+    // updates dropped because too many waited for the same entity
+    pub dropped_updates: u64,
+    pub queued_bytes: usize,
+}
+
+impl NetworkBandwidthStats {
+    pub fn saturation(&self) -> f32 {
+        if self.ticks == 0 {
+            0.
+        } else {
+            self.saturated_ticks as f32 / self.ticks as f32
+        }
+    }
+}
+
+struct NetworkPendingUpdates {
+    messages: VecDeque<Arc<str>>,
+    // grows every tick the updates wait, reset once some of them are sent
+    priority: f32,
+}
+
+// the updates of one entity always arrive in order, updates of different entities are reordered
+// when some of them wait for the budget or an update rate
+#[derive(Default)]
+pub(crate) struct NetworkBandwidth {
+    budget: Option<usize>,
+    // updates waiting for the budget
+    pending: HashMap<NetworkEntity, NetworkPendingUpdates>,
+    // updates of entities with an update rate, waiting until they're due
+    rate_limited: HashMap<NetworkEntity, NetworkPendingUpdates>,
+    stats: NetworkBandwidthStats,
+}
+
+impl NetworkBandwidth {
+    pub(crate) fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+        self.stats = NetworkBandwidthStats::default();
+    }
+
+    pub(crate) fn stats(&self) -> Option<NetworkBandwidthStats> {
+        self.budget.map(|_| NetworkBandwidthStats {
+            queued_bytes: self
+                .pending
+                .values()
+                .chain(self.rate_limited.values())
+                .flat_map(|pending| pending.messages.iter())
+                .map(|message| message.len())
+                .sum(),
+            ..self.stats
+        })
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        !self.pending.is_empty() || !self.rate_limited.is_empty()
+    }
+
+    // updates go straight to the socket, unless the player has a budget, updates of the entity
+    // still wait or the entity has an update rate
+    pub(crate) fn send(
+        &mut self,
+        socket: &mut NetworkSocket,
+        entity: NetworkEntity,
+        message: Arc<str>,
+        rate_limited: bool,
+    ) {
+        let waiting = self.pending.contains_key(&entity);
+        let waiting_for_rate = self.rate_limited.contains_key(&entity);
+        if self.budget.is_none() && !waiting && !waiting_for_rate && !rate_limited {
+            socket.send_shared(message);
+        } else {
+            // an entity's updates stay in the queue they're in, so they keep their order
+            let queue = if waiting || (!waiting_for_rate && !rate_limited) {
+                &mut self.pending
+            } else {
+                &mut self.rate_limited
+            };
+            let pending = queue
+                .entry(entity)
+                .or_insert_with(|| NetworkPendingUpdates {
+                    messages: VecDeque::new(),
+                    priority: 0.,
+                });
+            pending.messages.push_back(message);
+            if pending.messages.len() > MAX_PENDING_UPDATES {
+                pending.messages.pop_front();
+                self.stats.dropped_updates += 1;
+            }
+        }
+    }
+
+    // sends what's still waiting for the entity right away, before it's despawned for the player
+    pub(crate) fn flush_entity(&mut self, socket: &mut NetworkSocket, entity: NetworkEntity) {
+        let pending = self.pending.remove(&entity);
+        let rate_limited = self.rate_limited.remove(&entity);
+        for pending in pending.into_iter().chain(rate_limited) {
+            for message in pending.messages {
+                self.stats.sent_bytes += message.len() as u64;
+                socket.send_shared(message);
+            }
+        }
+    }
+
+    // updates for players that never had the entity are dropped
+    pub(crate) fn remove_entity(&mut self, entity: NetworkEntity) {
+        self.pending.remove(&entity);
+        self.rate_limited.remove(&entity);
+    }
+
+    // sends the updates of the entities with the highest priority first, until the budget is used,
+    // the priority is None for entities that aren't due this tick
+    pub(crate) fn flush<F>(&mut self, socket: &mut NetworkSocket, priority: F)
+    where
+        F: Fn(NetworkEntity) -> Option<f32>,
+    {
+        let budget = self.budget.unwrap_or(usize::MAX);
+        // due rate limited updates compete for the budget like any other
+        let due: Vec<NetworkEntity> = self
+            .rate_limited
+            .keys()
+            .filter(|entity| priority(**entity).is_some())
+            .copied()
+            .collect();
+        for entity in due {
+            let pending = self.rate_limited.remove(&entity).unwrap();
+            self.pending.insert(entity, pending);
+        }
+        let mut order = vec![];
+        for (entity, pending) in self.pending.iter_mut() {
+            if let Some(priority) = priority(*entity) {
+                pending.priority += priority;
+                order.push((*entity, pending.priority));
+            }
+        }
+        order.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        let mut sent = 0;
+        let mut deferred = 0;
+        for (entity, _) in order {
+            let pending = self.pending.get_mut(&entity).unwrap();
+            let mut sent_any = false;
+            while let Some(message) = pending.messages.front() {
+                // one update always goes out, so updates bigger than the budget aren't stuck
+                if sent > 0 && sent + message.len() > budget {
+                    break;
+                }
+                sent += message.len();
+                sent_any = true;
+                socket.send_shared(pending.messages.pop_front().unwrap());
+            }
+            deferred += pending.messages.len();
+            if pending.messages.is_empty() {
+                self.pending.remove(&entity);
+            } else if sent_any {
+                pending.priority = 0.;
+            }
+        }
+        // updates held by an update rate don't count as deferred
+        if self.budget.is_some() {
+            self.stats.ticks += 1;
+            self.stats.sent_bytes += sent as u64;
+            self.stats.deferred_updates += deferred as u64;
+            if deferred > 0 {
+                self.stats.saturated_ticks += 1;
+            }
+        }
+    }
+}
+
+// closer entities come first, when the player has a viewpoint and the entity a position
+pub(crate) fn update_priority(priority: Option<NetworkPriority>, distance: Option<f32>) -> f32 {
+    let priority = priority.unwrap_or_default().0;
+    if let Some(distance) = distance {
+        priority / (1. + distance)
+    } else {
+        priority
+    }
+}
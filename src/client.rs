@@ -1,13 +1,18 @@
 use crate::{
+    clock::NetworkClockSync,
     entity::NetworkEntity,
-    events::NetworkEventTraits,
+    events::{NetworkDeferredEvent, NetworkEntityDespawnReason, NetworkEventTraits},
+    input::{NetworkInputHistory, NetworkInputTraits},
+    lockstep::{NetworkLockstepClient, NetworkLockstepTraits, DEFAULT_INPUT_DELAY},
     messages::NetworkMessage,
+    network_type_name::NetworkTypeName,
     player::NetworkPlayer,
+    rollback::{NetworkRollbackInputTraits, NetworkRollbackSession, MAX_ROLLBACK_TICKS},
     serialized_struct::{NetworkSerializedStruct, NetworkSerializedStructMap},
 };
 use bevy::prelude::*;
 use bevy_nety_protocol::NetworkSocket;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub(crate) struct NetworkClientPlayer {
     pub(crate) handle: NetworkPlayer,
@@ -18,7 +23,16 @@ pub(crate) struct NetworkClientEntity {
     pub(crate) initialized: bool,
     pub(crate) exists: bool,
     pub(crate) local_entity: Option<Entity>,
-    pub(crate) owner: bool,
+    pub(crate) owner: Option<NetworkPlayer>,
+    pub(crate) prefab: Option<NetworkSerializedStruct>,
+    pub(crate) despawn_reason: NetworkEntityDespawnReason,
+    // spawned by this client and not yet confirmed by the server
+    pub(crate) pending: bool,
+    // the server confirmed the entity under a different NetworkEntity
+    pub(crate) remapped: bool,
+    pub(crate) parent: Option<NetworkEntity>,
+    // the parent has to be attached locally, once both entities are spawned
+    pub(crate) parent_changed: bool,
 }
 
 pub struct NetworkClient {
@@ -28,6 +42,23 @@ pub struct NetworkClient {
     pub(crate) players: Vec<NetworkClientPlayer>,
     pub(crate) existing_player_flag: bool,
     pub(crate) entities: HashMap<NetworkEntity, NetworkClientEntity>,
+    pub(crate) clock: NetworkClockSync,
+    pub(crate) interpolation_delay: f64,
+    pub(crate) inputs: HashMap<NetworkTypeName, NetworkInputHistory>,
+    // the newest input of each type sent before the clock could estimate the server tick
+    pub(crate) unsynced_inputs: HashMap<NetworkTypeName, NetworkSerializedStruct>,
+    pub(crate) lockstep: HashMap<NetworkTypeName, NetworkLockstepClient>,
+    pub(crate) lockstep_input_delay: u64,
+    pub(crate) rollback: Option<NetworkRollbackSession>,
+    pub(crate) rollback_input_delay: u64,
+    pub(crate) rollback_sync_test: Option<u64>,
+    // events sent since the last update, to the entity if there is one and the server otherwise
+    pub(crate) deferred_events: Vec<(Option<NetworkEntity>, NetworkDeferredEvent)>,
+    // the last value of each network resource received from the server
+    pub(crate) resources: HashMap<NetworkTypeName, NetworkSerializedStruct>,
+    // entities whose owner changed since the owner components were last updated
+    pub(crate) owner_changes: HashSet<NetworkEntity>,
+    pub(crate) owners_synced: bool,
 }
 
 impl NetworkClient {
@@ -39,44 +70,262 @@ impl NetworkClient {
             players: vec![],
             existing_player_flag: true,
             entities: HashMap::new(),
+            clock: NetworkClockSync::new(),
+            interpolation_delay: 0.,
+            inputs: HashMap::new(),
+            unsynced_inputs: HashMap::new(),
+            lockstep: HashMap::new(),
+            lockstep_input_delay: DEFAULT_INPUT_DELAY,
+            rollback: None,
+            rollback_input_delay: DEFAULT_INPUT_DELAY,
+            rollback_sync_test: None,
+            deferred_events: vec![],
+            resources: HashMap::new(),
+            owner_changes: HashSet::new(),
+            owners_synced: false,
         }
     }
 
+    // events are serialized and sent during the next network update rather than right away, after
+    // the entities spawned this frame, so their entity fields can refer to those entities
     pub fn send<T>(&mut self, event: T)
     where
         T: NetworkEventTraits,
     {
-        self.socket.send(
-            NetworkMessage::Event {
-                data: NetworkSerializedStruct::from_struct(&event),
-            }
-            .serialize(),
-        );
+        self.defer_event(None, event);
     }
 
+    // sent during the next network update, like send
     pub fn send_to_entity<T>(&mut self, entity: NetworkEntity, event: T)
     where
         T: NetworkEventTraits,
     {
-        self.socket.send(
+        self.defer_event(Some(entity), event);
+    }
+
+    fn defer_event<T>(&mut self, entity: Option<NetworkEntity>, event: T)
+    where
+        T: NetworkEventTraits,
+    {
+        self.deferred_events.push((
+            entity,
+            Box::new(move || NetworkSerializedStruct::from_struct(&event)),
+        ));
+    }
+
+    pub(crate) fn send_event(
+        &mut self,
+        entity: Option<NetworkEntity>,
+        data: NetworkSerializedStruct,
+    ) {
+        let message = if let Some(entity) = entity {
             NetworkMessage::EntityEvent {
                 entity,
                 from: Some(self.me),
-                data: NetworkSerializedStruct::from_struct(&event),
+                data,
+            }
+        } else {
+            NetworkMessage::Event { data }
+        };
+        self.socket.send(message.serialize());
+    }
+
+    // inputs are stamped with the server tick they are expected to arrive on, inputs sent
+    // before the clock is synchronized are held back and only the newest is sent once it is
+    pub fn send_input<T>(&mut self, input: T)
+    where
+        T: NetworkInputTraits,
+    {
+        let type_name = NetworkTypeName::of::<T>();
+        let input = NetworkSerializedStruct::from_struct(&input);
+        if self.clock.synchronized() {
+            self.send_input_data(type_name, input);
+        } else {
+            self.unsynced_inputs.insert(type_name, input);
+        }
+    }
+
+    pub(crate) fn send_unsynced_inputs(&mut self) {
+        if self.clock.synchronized() {
+            for (type_name, input) in std::mem::take(&mut self.unsynced_inputs) {
+                self.send_input_data(type_name, input);
+            }
+        }
+    }
+
+    fn send_input_data(&mut self, type_name: NetworkTypeName, input: NetworkSerializedStruct) {
+        if let Some(tick) = self.clock.server_tick(self.clock.rtt() / 2.) {
+            let inputs = self.inputs.entry(type_name).or_default().push(tick, input);
+            self.socket
+                .send(NetworkMessage::Input { inputs }.serialize());
+        }
+    }
+
+    // how many ticks in the future lockstep inputs are scheduled for
+    pub fn set_lockstep_input_delay(&mut self, ticks: u64) {
+        self.lockstep_input_delay = ticks;
+    }
+
+    pub fn lockstep_input_delay(&self) -> u64 {
+        self.lockstep_input_delay
+    }
+
+    // sends the input for the next lockstep tick and returns which tick it was scheduled for
+    pub fn send_lockstep_input<T>(&mut self, input: T) -> u64
+    where
+        T: NetworkLockstepTraits,
+    {
+        let lockstep = self.lockstep.entry(NetworkTypeName::of::<T>()).or_default();
+        let tick = if let Some(next_input_tick) = lockstep.next_input_tick {
+            next_input_tick
+        } else {
+            // the first input is delayed, so the ticks before it are filled with default input
+            let start_tick = lockstep.start_tick;
+            for tick in start_tick..start_tick + self.lockstep_input_delay {
+                self.socket.send(
+                    NetworkMessage::LockstepInput {
+                        tick,
+                        data: NetworkSerializedStruct::from_struct(&T::default()),
+                    }
+                    .serialize(),
+                );
+            }
+            start_tick + self.lockstep_input_delay
+        };
+        lockstep.next_input_tick = Some(tick + 1);
+        self.socket.send(
+            NetworkMessage::LockstepInput {
+                tick,
+                data: NetworkSerializedStruct::from_struct(&input),
             }
             .serialize(),
         );
+        tick
+    }
+
+    pub fn send_lockstep_checksum(&mut self, tick: u64, checksum: u64) {
+        self.socket
+            .send(NetworkMessage::LockstepChecksum { tick, checksum }.serialize());
+    }
+
+    pub(crate) fn send_state_request(&mut self, data: NetworkSerializedStruct) {
+        self.socket
+            .send(NetworkMessage::StateRequest { data }.serialize());
+    }
+
+    pub fn set_rollback_input_delay(&mut self, ticks: u64) {
+        self.rollback_input_delay = ticks;
+    }
+
+    pub fn rollback_input_delay(&self) -> u64 {
+        self.rollback_input_delay
+    }
+
+    // rolls back and resimulates this many ticks every tick, to find nondeterminism locally,
+    // at most as many ticks as there are snapshots kept for
+    pub fn set_rollback_sync_test(&mut self, ticks: Option<u64>) {
+        self.rollback_sync_test = ticks.map(|ticks| ticks.min(MAX_ROLLBACK_TICKS));
+    }
+
+    // the next tick the rollback simulation will run
+    pub fn rollback_tick(&self) -> Option<u64> {
+        self.rollback.as_ref().map(|rollback| rollback.tick())
+    }
+
+    // sends the input for the next rollback tick and returns which tick it was scheduled for
+    pub fn send_rollback_input<T>(&mut self, input: T) -> u64
+    where
+        T: NetworkRollbackInputTraits,
+    {
+        let me = self.me;
+        let rollback = self
+            .rollback
+            .get_or_insert_with(|| NetworkRollbackSession::new(NetworkTypeName::of::<T>()));
+        let mut inputs = vec![];
+        let mut tick = if let Some(next_input_tick) = rollback.next_input_tick {
+            next_input_tick
+        } else {
+            // the first input is delayed, so the ticks before it are filled with default input
+            for _ in 0..self.rollback_input_delay {
+                inputs.push(T::default());
+            }
+            rollback.tick()
+        };
+        inputs.push(input);
+        for input in inputs {
+            let data = NetworkSerializedStruct::from_struct(&input);
+            rollback.receive(me, tick, data.clone());
+            self.socket
+                .send(NetworkMessage::RollbackInput { tick, data }.serialize());
+            tick += 1;
+        }
+        rollback.next_input_tick = Some(tick);
+        tick - 1
+    }
+
+    // how far behind the server clock this client renders entities, used by lag compensation
+    pub fn set_interpolation_delay(&mut self, seconds: f64) {
+        self.interpolation_delay = seconds;
+        self.socket
+            .send(NetworkMessage::InterpolationDelay { seconds }.serialize());
+    }
+
+    pub fn interpolation_delay(&self) -> f64 {
+        self.interpolation_delay
     }
 
     pub(crate) fn players(&self) -> Vec<NetworkPlayer> {
         self.players.iter().map(|p| p.handle).collect()
     }
 
+    // the entities whose owner changed since the last call, None the first time since every
+    // entity needs its owner component checked then
+    pub(crate) fn take_owner_changes(&mut self) -> Option<HashSet<NetworkEntity>> {
+        let changes = std::mem::take(&mut self.owner_changes);
+        if self.owners_synced {
+            Some(changes)
+        } else {
+            self.owners_synced = true;
+            None
+        }
+    }
+
     pub(crate) fn is_entity_owner(&mut self, entity: NetworkEntity) -> bool {
         if let Some(entity) = self.entities.get(&entity) {
-            entity.owner
+            entity.owner == Some(self.me)
         } else {
             false
         }
     }
+
+    pub fn request_entity_ownership(&mut self, entity: NetworkEntity) {
+        self.socket.send(
+            NetworkMessage::EntityOwnershipRequest {
+                entity,
+                release: false,
+            }
+            .serialize(),
+        );
+    }
+
+    pub fn release_entity_ownership(&mut self, entity: NetworkEntity) {
+        self.socket.send(
+            NetworkMessage::EntityOwnershipRequest {
+                entity,
+                release: true,
+            }
+            .serialize(),
+        );
+    }
+
+    // true while an entity spawned by this client waits for the server to confirm it
+    pub fn is_entity_pending(&self, entity: NetworkEntity) -> bool {
+        self.entities
+            .get(&entity)
+            .map_or(false, |entity| entity.pending)
+    }
+
+    pub fn entity_owner(&self, entity: NetworkEntity) -> Option<NetworkPlayer> {
+        self.entities.get(&entity).and_then(|entity| entity.owner)
+    }
 }
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+// how many round trips to keep around when estimating the server clock
+const MAX_SAMPLES: usize = 8;
+// how often to take a new sample once the sample window is full
+const SAMPLE_INTERVAL: f64 = 1.;
+
+#[derive(Default)]
+pub struct NetworkClock {
+    pub(crate) synchronized: bool,
+    pub(crate) server_time: f64,
+    pub(crate) server_tick: u64,
+    pub(crate) server_tick_rate: f64,
+    pub(crate) rtt: f64,
+}
+
+impl NetworkClock {
+    pub fn synchronized(&self) -> bool {
+        self.synchronized
+    }
+
+    // estimated seconds since the server started
+    pub fn server_time(&self) -> f64 {
+        self.server_time
+    }
+
+    pub fn server_tick(&self) -> u64 {
+        self.server_tick
+    }
+
+    // estimated server ticks per second, zero if not yet known
+    pub fn server_tick_rate(&self) -> f64 {
+        self.server_tick_rate
+    }
+
+    // round trip time in seconds
+    pub fn rtt(&self) -> f64 {
+        self.rtt
+    }
+
+    pub fn server_time_to_tick(&self, server_time: f64) -> u64 {
+        let ticks = (server_time - self.server_time) * self.server_tick_rate;
+        (self.server_tick as f64 + ticks).max(0.).round() as u64
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct NetworkClockSample {
+    pub(crate) rtt: f64,
+    pub(crate) offset: f64,
+    pub(crate) server_time: f64,
+    pub(crate) server_tick: u64,
+}
+
+pub(crate) struct NetworkClockSync {
+    started: Instant,
+    samples: VecDeque<NetworkClockSample>,
+    request_in_flight: bool,
+    last_request: Option<f64>,
+}
+
+impl NetworkClockSync {
+    pub(crate) fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            samples: VecDeque::new(),
+            request_in_flight: false,
+            last_request: None,
+        }
+    }
+
+    pub(crate) fn local_time(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    pub(crate) fn should_request(&self) -> bool {
+        if self.request_in_flight {
+            false
+        } else if self.samples.len() < MAX_SAMPLES {
+            true
+        } else if let Some(last_request) = self.last_request {
+            self.local_time() - last_request >= SAMPLE_INTERVAL
+        } else {
+            true
+        }
+    }
+
+    pub(crate) fn start_request(&mut self) -> f64 {
+        let now = self.local_time();
+        self.request_in_flight = true;
+        self.last_request = Some(now);
+        now
+    }
+
+    pub(crate) fn receive_response(
+        &mut self,
+        client_time: f64,
+        server_time: f64,
+        server_tick: u64,
+    ) {
+        let now = self.local_time();
+        self.request_in_flight = false;
+        let rtt = (now - client_time).max(0.);
+        self.add_sample(NetworkClockSample {
+            rtt,
+            offset: server_time + rtt / 2. - now,
+            server_time,
+            server_tick,
+        });
+    }
+
+    pub(crate) fn add_sample(&mut self, sample: NetworkClockSample) {
+        self.samples.push_back(sample);
+        while self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    pub(crate) fn synchronized(&self) -> bool {
+        !self.samples.is_empty()
+    }
+
+    // only the faster half of the round trips are trusted, so a lag spike doesn't skew the estimate
+    fn trusted_samples(&self) -> Vec<NetworkClockSample> {
+        let mut samples: Vec<NetworkClockSample> = self.samples.iter().copied().collect();
+        samples.sort_by(|a, b| a.rtt.partial_cmp(&b.rtt).unwrap());
+        samples.truncate((samples.len() + 1) / 2);
+        samples
+    }
+
+    pub(crate) fn rtt(&self) -> f64 {
+        let samples = self.trusted_samples();
+        if samples.is_empty() {
+            0.
+        } else {
+            samples.iter().map(|s| s.rtt).sum::<f64>() / samples.len() as f64
+        }
+    }
+
+    pub(crate) fn offset(&self) -> f64 {
+        let samples = self.trusted_samples();
+        if samples.is_empty() {
+            0.
+        } else {
+            samples.iter().map(|s| s.offset).sum::<f64>() / samples.len() as f64
+        }
+    }
+
+    pub(crate) fn tick_rate(&self) -> f64 {
+        if let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) {
+            let elapsed = last.server_time - first.server_time;
+            if elapsed > 0. {
+                return (last.server_tick - first.server_tick) as f64 / elapsed;
+            }
+        }
+        0.
+    }
+
+    pub(crate) fn server_time(&self) -> f64 {
+        self.local_time() + self.offset()
+    }
+
+    // estimated server tick, `ahead` seconds from now, unknown until the first sample arrives
+    pub(crate) fn server_tick(&self, ahead: f64) -> Option<u64> {
+        let latest = self.samples.back()?;
+        let ticks = (self.server_time() + ahead - latest.server_time) * self.tick_rate();
+        Some(latest.server_tick + ticks.max(0.).round() as u64)
+    }
+
+    pub(crate) fn update(&self, clock: &mut NetworkClock) {
+        clock.synchronized = self.synchronized();
+        if let Some(server_tick) = self.server_tick(0.) {
+            clock.server_time = self.server_time();
+            clock.server_tick_rate = self.tick_rate();
+            clock.rtt = self.rtt();
+            clock.server_tick = server_tick;
+        }
+    }
+}
@@ -4,7 +4,10 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{events::NetworkEventTraits, serialized_struct::NetworkSerializedStruct};
+use crate::{
+    events::{NetworkDeferredEvent, NetworkEntityDespawnReason, NetworkEventTraits},
+    serialized_struct::NetworkSerializedStruct,
+};
 
 #[derive(Component, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct NetworkEntity(pub Uuid);
@@ -15,6 +18,33 @@ impl NetworkEntity {
     }
 }
 
+// how the server answers a client asking to own an entity
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NetworkOwnershipPolicy {
+    // server code answers the NetworkOwnershipRequestEvent
+    #[default]
+    Manual,
+    Approve,
+    ApproveIfUnowned,
+    Deny,
+}
+
+// what happens to an entity when its owner leaves
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum NetworkOwnerLeavePolicy {
+    // ownership goes back to the server
+    #[default]
+    RevertToServer,
+    Despawn,
+    // the server holds the entity and refuses ownership requests for `seconds`, after which it
+    // stays with the server like RevertToServer. a reconnecting client joins as a new player, so
+    // nothing is handed back automatically: server code has to recognize the client (player data,
+    // a login, ...) and call reclaim_reserved_entities with its old and new player
+    Reserve {
+        seconds: f64,
+    },
+}
+
 impl Default for NetworkEntity {
     fn default() -> Self {
         Self::new()
@@ -23,7 +53,7 @@ impl Default for NetworkEntity {
 
 #[derive(Component, Default)]
 pub struct NetworkEntityOwner {
-    pub(crate) events: VecDeque<NetworkSerializedStruct>,
+    pub(crate) events: VecDeque<NetworkDeferredEvent>,
 }
 
 impl NetworkEntityOwner {
@@ -31,7 +61,20 @@ impl NetworkEntityOwner {
     where
         T: NetworkEventTraits,
     {
-        self.events
-            .push_back(NetworkSerializedStruct::from_struct(&event));
+        self.events.push_back(Box::new(move || {
+            NetworkSerializedStruct::from_struct(&event)
+        }));
     }
 }
+
+// keeps the local entity around on clients after the server despawns it,
+// it's despawned once this component is removed
+#[derive(Component, Default)]
+pub struct NetworkDelayDespawn;
+
+// added in place of NetworkEntity to entities kept around by NetworkDelayDespawn
+#[derive(Component, Debug, Clone)]
+pub struct NetworkDespawned {
+    pub network_entity: NetworkEntity,
+    pub reason: NetworkEntityDespawnReason,
+}
@@ -0,0 +1,242 @@
+use crate::entity::NetworkEntity;
+use bevy::prelude::*;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{any::TypeId, cell::RefCell, collections::HashMap, sync::Arc};
+
+thread_local! {
+    static ENTITY_MAP: RefCell<Option<Arc<NetworkEntityMap>>> = RefCell::new(None);
+}
+
+pub(crate) const UNMAPPED_ENTITY: &str = "entity is not a network entity on this side";
+
+// translates between local entities and network entities while events are (de)serialized
+#[derive(Default, Clone)]
+pub(crate) struct NetworkEntityMap {
+    to_network: HashMap<Entity, NetworkEntity>,
+    to_local: HashMap<NetworkEntity, Entity>,
+}
+
+impl NetworkEntityMap {
+    fn from_world(world: &mut World) -> Self {
+        let mut map = Self::default();
+        let mut query = world.query::<(Entity, &NetworkEntity)>();
+        for (entity, network_entity) in query.iter(world) {
+            map.insert(entity, *network_entity);
+        }
+        map
+    }
+
+    fn insert(&mut self, entity: Entity, network_entity: NetworkEntity) {
+        if let Some(previous) = self.to_network.insert(entity, network_entity) {
+            self.to_local.remove(&previous);
+        }
+        self.to_local.insert(network_entity, entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(network_entity) = self.to_network.remove(&entity) {
+            self.to_local.remove(&network_entity);
+        }
+    }
+
+    pub(crate) fn local(&self, network_entity: NetworkEntity) -> Option<Entity> {
+        self.to_local.get(&network_entity).copied()
+    }
+
+    // runs f with the map used by the network_entity serde adapters
+    pub(crate) fn scope<R>(map: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        let previous = ENTITY_MAP.with(|current| current.replace(Some(map.clone())));
+        let result = f();
+        ENTITY_MAP.with(|current| *current.borrow_mut() = previous);
+        result
+    }
+}
+
+// finds the local entity of a NetworkEntity and the other way around, the network applies the
+// network entities added and removed at the start and end of every update and at the end of the
+// frame, and keeps it current while it spawns and despawns
+#[derive(Default)]
+pub struct NetworkEntities {
+    map: Arc<NetworkEntityMap>,
+}
+
+impl NetworkEntities {
+    pub fn get(&self, network_entity: NetworkEntity) -> Option<Entity> {
+        self.map.local(network_entity)
+    }
+
+    pub fn network_entity(&self, entity: Entity) -> Option<NetworkEntity> {
+        self.map.to_network.get(&entity).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.to_local.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.to_local.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NetworkEntity, Entity)> + '_ {
+        self.map
+            .to_local
+            .iter()
+            .map(|(network_entity, entity)| (*network_entity, *entity))
+    }
+
+    pub(crate) fn refresh(world: &mut World) -> Arc<NetworkEntityMap> {
+        let map = Arc::new(NetworkEntityMap::from_world(world));
+        world.insert_resource(NetworkEntities { map: map.clone() });
+        map
+    }
+
+    // applies the network entities added, changed and removed since the network last ran
+    pub(crate) fn update(world: &mut World) {
+        if !world.contains_resource::<NetworkEntities>() {
+            Self::refresh(world);
+            return;
+        }
+        let removed = Self::removed(world);
+        let mut query = world.query_filtered::<(Entity, &NetworkEntity), Changed<NetworkEntity>>();
+        let changed: Vec<(Entity, NetworkEntity)> = query
+            .iter(world)
+            .map(|(entity, network_entity)| (entity, *network_entity))
+            .collect();
+        if !removed.is_empty() || !changed.is_empty() {
+            let mut entities = world.get_resource_mut::<NetworkEntities>().unwrap();
+            let map = Arc::make_mut(&mut entities.map);
+            for entity in removed {
+                map.remove(entity);
+            }
+            for (entity, network_entity) in changed {
+                map.insert(entity, network_entity);
+            }
+        }
+        // a removal made where nothing saw it before the frame ended leaves a dead entity behind
+        if Self::current_readonly(world).to_network.len() != Self::count(world) {
+            Self::refresh(world);
+        }
+    }
+
+    // entities with a NetworkEntity, counted per archetype so it stays cheap every update
+    fn count(world: &World) -> usize {
+        if let Some(id) = world.components().get_id(TypeId::of::<NetworkEntity>()) {
+            world
+                .archetypes()
+                .iter()
+                .filter(|archetype| archetype.contains(id))
+                .map(|archetype| archetype.len())
+                .sum()
+        } else {
+            0
+        }
+    }
+
+    // the component can be removed and inserted again in the same frame
+    fn removed(world: &World) -> Vec<Entity> {
+        world
+            .removed::<NetworkEntity>()
+            .filter(|entity| world.get::<NetworkEntity>(*entity).is_none())
+            .collect()
+    }
+
+    // the map as of the last change, for code that can't refresh it
+    pub(crate) fn current_readonly(world: &World) -> Arc<NetworkEntityMap> {
+        world
+            .get_resource::<NetworkEntities>()
+            .map(|entities| entities.map.clone())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn current(world: &mut World) -> Arc<NetworkEntityMap> {
+        if let Some(entities) = world.get_resource::<NetworkEntities>() {
+            entities.map.clone()
+        } else {
+            Self::refresh(world)
+        }
+    }
+
+    pub(crate) fn insert(world: &mut World, entity: Entity, network_entity: NetworkEntity) {
+        let mut entities = world.get_resource_or_insert_with(NetworkEntities::default);
+        Arc::make_mut(&mut entities.map).insert(entity, network_entity);
+    }
+
+    pub(crate) fn remove(world: &mut World, entity: Entity) {
+        if let Some(mut entities) = world.get_resource_mut::<NetworkEntities>() {
+            Arc::make_mut(&mut entities.map).remove(entity);
+        }
+    }
+}
+
+pub(crate) fn remove_network_entities(world: &mut World) {
+    let removed = NetworkEntities::removed(world);
+    if removed.is_empty() {
+        return;
+    }
+    if let Some(mut entities) = world.get_resource_mut::<NetworkEntities>() {
+        let map = Arc::make_mut(&mut entities.map);
+        for entity in removed {
+            map.remove(entity);
+        }
+    }
+}
+
+fn to_network(entity: Entity) -> Option<NetworkEntity> {
+    ENTITY_MAP.with(|map| {
+        map.borrow()
+            .as_ref()
+            .and_then(|map| map.to_network.get(&entity).copied())
+    })
+}
+
+fn to_local(network_entity: NetworkEntity) -> Option<Entity> {
+    ENTITY_MAP.with(|map| {
+        map.borrow()
+            .as_ref()
+            .and_then(|map| map.to_local.get(&network_entity).copied())
+    })
+}
+
+// use on Entity fields with #[serde(with = "network_entity")], the entity is sent as its
+// NetworkEntity and turned back into the local entity when received, events and payloads
+// with entities that aren't network entities on both sides fail to deserialize and are dropped
+pub mod network_entity {
+    use super::*;
+
+    pub fn serialize<S>(entity: &Entity, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        to_network(*entity).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Entity, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let network_entity = Option::<NetworkEntity>::deserialize(deserializer)?;
+        network_entity
+            .and_then(to_local)
+            .ok_or_else(|| D::Error::custom(UNMAPPED_ENTITY))
+    }
+}
+
+// same as network_entity for Option<Entity> fields, entities that can't be mapped become None
+pub mod network_entity_option {
+    use super::*;
+
+    pub fn serialize<S>(entity: &Option<Entity>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        entity.and_then(to_network).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Entity>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let network_entity = Option::<NetworkEntity>::deserialize(deserializer)?;
+        Ok(network_entity.and_then(to_local))
+    }
+}
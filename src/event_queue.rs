@@ -1,15 +1,43 @@
 use crate::{
     entity::NetworkEntity,
+    entity_map::{NetworkEntities, NetworkEntityMap},
     events::{
-        NetworkConnectEvent, NetworkConnectingEvent, NetworkDisconnectEvent,
-        NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkConnectEvent, NetworkConnectingEvent, NetworkDesyncEvent, NetworkDisconnectEvent,
+        NetworkEntityDespawnEvent, NetworkEntityEventDroppedEvent, NetworkEntityOrphanedEvent,
+        NetworkEntityOwnershipChangedEvent, NetworkEntitySpawnEvent, NetworkOwnershipRequestEvent,
+        NetworkOwnershipResponseEvent, NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkSpawnRequestEvent, NetworkSpawnResponseEvent,
     },
+    lockstep::NetworkLockstepInputs,
+    network_type_name::NetworkTypeName,
     player::NetworkPlayer,
     registry::NetworkRegistry,
     serialized_struct::NetworkSerializedStruct,
 };
 use bevy::{app::Events, prelude::*};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+
+pub(crate) const DEFAULT_ENTITY_EVENT_BUFFER_TICKS: u64 = 60;
+
+// entity events wait here for their entity to show up, for up to this many network updates
+pub(crate) struct NetworkEntityEventBuffer {
+    pub(crate) ticks: u64,
+    events: VecDeque<(
+        NetworkEntity,
+        Option<NetworkPlayer>,
+        NetworkSerializedStruct,
+        u64,
+    )>,
+}
+
+impl Default for NetworkEntityEventBuffer {
+    fn default() -> Self {
+        Self {
+            ticks: DEFAULT_ENTITY_EVENT_BUFFER_TICKS,
+            events: VecDeque::new(),
+        }
+    }
+}
 
 #[derive(Default)]
 pub(crate) struct EventQueue {
@@ -25,6 +53,21 @@ pub(crate) struct EventQueue {
         Option<NetworkPlayer>,
         NetworkSerializedStruct,
     )>,
+    lockstep_ticks: VecDeque<(NetworkTypeName, u64, NetworkLockstepInputs)>,
+    desync_events: VecDeque<NetworkDesyncEvent>,
+    entity_spawn_events: VecDeque<NetworkEntitySpawnEvent>,
+    entity_despawn_events: VecDeque<NetworkEntityDespawnEvent>,
+    entity_ownership_changes:
+        VecDeque<(NetworkEntity, Option<NetworkPlayer>, Option<NetworkPlayer>)>,
+    entity_orphaned_events: VecDeque<NetworkEntityOrphanedEvent>,
+    spawn_request_events: VecDeque<NetworkSpawnRequestEvent>,
+    spawn_response_events: VecDeque<NetworkSpawnResponseEvent>,
+    ownership_requests: VecDeque<(NetworkPlayer, NetworkEntity)>,
+    ownership_responses: VecDeque<(NetworkEntity, bool, bool)>,
+    // None data removes the resource
+    resources: VecDeque<(NetworkTypeName, Option<NetworkSerializedStruct>)>,
+    state_requests: VecDeque<(NetworkPlayer, NetworkSerializedStruct)>,
+    pub(crate) entity_event_buffer: NetworkEntityEventBuffer,
 }
 
 impl EventQueue {
@@ -65,6 +108,76 @@ impl EventQueue {
         self.network_entity_events.push_back((entity, from, event));
     }
 
+    pub(crate) fn lockstep_tick(
+        &mut self,
+        type_name: NetworkTypeName,
+        tick: u64,
+        inputs: NetworkLockstepInputs,
+    ) {
+        self.lockstep_ticks.push_back((type_name, tick, inputs));
+    }
+
+    pub(crate) fn desync(&mut self, event: NetworkDesyncEvent) {
+        self.desync_events.push_back(event);
+    }
+
+    pub(crate) fn entity_spawn(&mut self, event: NetworkEntitySpawnEvent) {
+        self.entity_spawn_events.push_back(event);
+    }
+
+    pub(crate) fn entity_despawn(&mut self, event: NetworkEntityDespawnEvent) {
+        self.entity_despawn_events.push_back(event);
+    }
+
+    pub(crate) fn entity_ownership_changed(
+        &mut self,
+        entity: NetworkEntity,
+        old_owner: Option<NetworkPlayer>,
+        new_owner: Option<NetworkPlayer>,
+    ) {
+        self.entity_ownership_changes
+            .push_back((entity, old_owner, new_owner));
+    }
+
+    pub(crate) fn entity_orphaned(&mut self, event: NetworkEntityOrphanedEvent) {
+        self.entity_orphaned_events.push_back(event);
+    }
+
+    pub(crate) fn spawn_request(&mut self, event: NetworkSpawnRequestEvent) {
+        self.spawn_request_events.push_back(event);
+    }
+
+    pub(crate) fn spawn_response(&mut self, event: NetworkSpawnResponseEvent) {
+        self.spawn_response_events.push_back(event);
+    }
+
+    pub(crate) fn ownership_request(&mut self, player: NetworkPlayer, entity: NetworkEntity) {
+        self.ownership_requests.push_back((player, entity));
+    }
+
+    pub(crate) fn ownership_response(
+        &mut self,
+        entity: NetworkEntity,
+        release: bool,
+        approved: bool,
+    ) {
+        self.ownership_responses
+            .push_back((entity, release, approved));
+    }
+
+    pub(crate) fn resource(&mut self, data: NetworkSerializedStruct) {
+        self.resources
+            .push_back((data.type_name.clone(), Some(data)));
+    }
+
+    pub(crate) fn resource_removed(&mut self, type_name: NetworkTypeName) {
+        self.resources.push_back((type_name, None));
+    }
+
+    pub(crate) fn state_request(&mut self, player: NetworkPlayer, data: NetworkSerializedStruct) {
+        self.state_requests.push_back((player, data));
+    }
+
     pub(crate) fn send_to_world(&mut self, world: &mut World, registry: &mut NetworkRegistry) {
         while let Some(connect_event) = self.connect_events.pop_front() {
             let mut events = world
@@ -96,31 +209,167 @@ impl EventQueue {
                 .unwrap();
             events.send(player_leave_event);
         }
+        while let Some(desync_event) = self.desync_events.pop_front() {
+            let mut events = world
+                .get_resource_mut::<Events<NetworkDesyncEvent>>()
+                .unwrap();
+            events.send(desync_event);
+        }
+        while let Some(entity_spawn_event) = self.entity_spawn_events.pop_front() {
+            let mut events = world
+                .get_resource_mut::<Events<NetworkEntitySpawnEvent>>()
+                .unwrap();
+            events.send(entity_spawn_event);
+        }
+        // entities despawned this update still get the events that were sent before the despawn
+        let mut despawned = HashMap::new();
+        while let Some(entity_despawn_event) = self.entity_despawn_events.pop_front() {
+            despawned.insert(
+                entity_despawn_event.network_entity,
+                entity_despawn_event.entity,
+            );
+            let mut events = world
+                .get_resource_mut::<Events<NetworkEntityDespawnEvent>>()
+                .unwrap();
+            events.send(entity_despawn_event);
+        }
+        while let Some(entity_orphaned_event) = self.entity_orphaned_events.pop_front() {
+            let mut events = world
+                .get_resource_mut::<Events<NetworkEntityOrphanedEvent>>()
+                .unwrap();
+            events.send(entity_orphaned_event);
+        }
+        while let Some(spawn_request_event) = self.spawn_request_events.pop_front() {
+            let mut events = world
+                .get_resource_mut::<Events<NetworkSpawnRequestEvent>>()
+                .unwrap();
+            events.send(spawn_request_event);
+        }
+        while let Some(spawn_response_event) = self.spawn_response_events.pop_front() {
+            let mut events = world
+                .get_resource_mut::<Events<NetworkSpawnResponseEvent>>()
+                .unwrap();
+            events.send(spawn_response_event);
+        }
+        let entity_map = NetworkEntities::current(world);
+        while let Some((type_name, tick, inputs)) = self.lockstep_ticks.pop_front() {
+            if let Some(entry) = registry.get_entry_from_type_name(&type_name) {
+                if let Some(lockstep) = &mut entry.lockstep {
+                    (lockstep.send_to_world)(world, tick, inputs);
+                }
+            }
+        }
+        while let Some((type_name, data)) = self.resources.pop_front() {
+            if let Some(entry) = registry.get_entry_from_type_name(&type_name) {
+                if let Some(resource) = &entry.resource {
+                    if let Some(data) = data {
+                        NetworkEntityMap::scope(&entity_map, || (resource.insert)(world, data));
+                    } else {
+                        (resource.remove)(world);
+                    }
+                }
+            }
+        }
+        while let Some((player, data)) = self.state_requests.pop_front() {
+            if let Some(entry) = registry.get_entry_from_serialized(&data) {
+                if let Some(state) = &entry.state {
+                    (state.send_request_to_world)(world, player, data);
+                }
+            }
+        }
         while let Some(network_event) = self.network_events.pop_front() {
             if let Some(entry) = registry.get_entry_from_serialized(&network_event) {
                 if let Some(event) = &mut entry.event {
-                    (event.send_to_world)(world, network_event);
+                    NetworkEntityMap::scope(&entity_map, || {
+                        (event.send_to_world)(world, network_event)
+                    });
                 }
             }
         }
         while let Some((from, network_server_event)) = self.network_server_events.pop_front() {
             if let Some(entry) = registry.get_entry_from_serialized(&network_server_event) {
                 if let Some(event) = &mut entry.event {
-                    (event.send_to_server_world)(world, from, network_server_event);
+                    NetworkEntityMap::scope(&entity_map, || {
+                        (event.send_to_server_world)(world, from, network_server_event)
+                    });
                 }
             }
         }
-        let mut network_entity_query = world.query::<(Entity, &NetworkEntity)>();
-        while let Some((network_entity, from, network_entity_event)) =
-            self.network_entity_events.pop_front()
+        while let Some((network_entity, old_owner, new_owner)) =
+            self.entity_ownership_changes.pop_front()
+        {
+            if let Some(entity) = entity_map.local(network_entity) {
+                let mut events = world
+                    .get_resource_mut::<Events<NetworkEntityOwnershipChangedEvent>>()
+                    .unwrap();
+                events.send(NetworkEntityOwnershipChangedEvent {
+                    entity,
+                    network_entity,
+                    old_owner,
+                    new_owner,
+                });
+            }
+        }
+        while let Some((player, network_entity)) = self.ownership_requests.pop_front() {
+            if let Some(entity) = entity_map.local(network_entity) {
+                let mut events = world
+                    .get_resource_mut::<Events<NetworkOwnershipRequestEvent>>()
+                    .unwrap();
+                events.send(NetworkOwnershipRequestEvent {
+                    player,
+                    entity,
+                    network_entity,
+                });
+            }
+        }
+        while let Some((network_entity, release, approved)) = self.ownership_responses.pop_front() {
+            if let Some(entity) = entity_map.local(network_entity) {
+                let mut events = world
+                    .get_resource_mut::<Events<NetworkOwnershipResponseEvent>>()
+                    .unwrap();
+                events.send(NetworkOwnershipResponseEvent {
+                    entity,
+                    network_entity,
+                    release,
+                    approved,
+                });
+            }
+        }
+        // buffered events go first, so events for the same entity keep their order
+        let buffer_ticks = self.entity_event_buffer.ticks;
+        let buffered = std::mem::take(&mut self.entity_event_buffer.events);
+        let received = self
+            .network_entity_events
+            .drain(..)
+            .map(|(network_entity, from, data)| (network_entity, from, data, buffer_ticks));
+        for (network_entity, from, network_entity_event, ticks_left) in
+            buffered.into_iter().chain(received)
         {
             if let Some(entry) = registry.get_entry_from_serialized(&network_entity_event) {
                 if let Some(event) = &mut entry.entity_event {
-                    let entity = network_entity_query
-                        .iter(world)
-                        .find(|(_, ne)| **ne == network_entity);
-                    if let Some((entity, _)) = entity {
-                        (event.send_to_world)(world, entity, from, network_entity_event);
+                    let entity = entity_map
+                        .local(network_entity)
+                        .or_else(|| despawned.get(&network_entity).copied());
+                    if let Some(entity) = entity {
+                        NetworkEntityMap::scope(&entity_map, || {
+                            (event.send_to_world)(world, entity, from, network_entity_event)
+                        });
+                    } else if ticks_left > 0 {
+                        self.entity_event_buffer.events.push_back((
+                            network_entity,
+                            from,
+                            network_entity_event,
+                            ticks_left - 1,
+                        ));
+                    } else {
+                        let mut events = world
+                            .get_resource_mut::<Events<NetworkEntityEventDroppedEvent>>()
+                            .unwrap();
+                        events.send(NetworkEntityEventDroppedEvent {
+                            network_entity,
+                            from,
+                            type_name: network_entity_event.type_name,
+                        });
                     }
                 }
             }
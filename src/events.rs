@@ -1,8 +1,11 @@
+use crate::entity::{NetworkEntity, NetworkOwnerLeavePolicy};
+use crate::network_type_name::NetworkTypeName;
 use crate::player::NetworkPlayer;
+use crate::serialized_struct::NetworkSerializedStruct;
 use bevy::ecs::system::Resource;
 use bevy::prelude::Entity;
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug)]
 pub struct NetworkConnectEvent {
@@ -29,6 +32,106 @@ pub struct NetworkPlayerJoinEvent {
 pub struct NetworkPlayerLeaveEvent {
     pub player: NetworkPlayer,
 }
+
+#[derive(Debug, Clone)]
+pub struct NetworkDesyncEvent {
+    pub tick: u64,
+    // empty when rollback couldn't rewind past an entity spawn or despawn
+    pub checksums: Vec<(NetworkPlayer, u64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkSyncTestMismatchEvent {
+    pub tick: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkEntitySpawnEvent {
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetworkEntityDespawnReason {
+    Destroyed,
+    Irrelevant,
+    // the server rejected an entity this client spawned
+    Rejected,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkEntityOwnershipChangedEvent {
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+    pub old_owner: Option<NetworkPlayer>,
+    pub new_owner: Option<NetworkPlayer>,
+}
+
+// the local entity is already gone if the policy was to despawn it
+#[derive(Debug, Clone)]
+pub struct NetworkEntityOrphanedEvent {
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+    pub owner: NetworkPlayer,
+    pub policy: NetworkOwnerLeavePolicy,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkSpawnRequestEvent {
+    pub player: NetworkPlayer,
+    pub network_entity: NetworkEntity,
+    pub prefab: Option<NetworkSerializedStruct>,
+}
+
+impl NetworkSpawnRequestEvent {
+    pub fn prefab<T>(&self) -> Option<T>
+    where
+        T: DeserializeOwned,
+    {
+        self.prefab
+            .as_ref()
+            .and_then(|prefab| prefab.to_struct::<T>())
+    }
+}
+
+// an entity event was dropped, because its entity didn't show up in time
+#[derive(Debug, Clone)]
+pub struct NetworkEntityEventDroppedEvent {
+    pub network_entity: NetworkEntity,
+    pub from: Option<NetworkPlayer>,
+    pub type_name: NetworkTypeName,
+}
+
+// confirmed is the NetworkEntity the server uses, or None if the spawn was rejected
+#[derive(Debug, Clone)]
+pub struct NetworkSpawnResponseEvent {
+    pub entity: Entity,
+    pub requested: NetworkEntity,
+    pub confirmed: Option<NetworkEntity>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkOwnershipRequestEvent {
+    pub player: NetworkPlayer,
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkOwnershipResponseEvent {
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+    pub release: bool,
+    pub approved: bool,
+}
+
+// the local entity is already gone, unless it had NetworkDelayDespawn
+#[derive(Debug, Clone)]
+pub struct NetworkEntityDespawnEvent {
+    pub entity: Entity,
+    pub network_entity: NetworkEntity,
+    pub reason: NetworkEntityDespawnReason,
+}
 pub struct NetworkEvent<T: Resource> {
     pub data: T,
 }
@@ -44,5 +147,8 @@ pub struct NetworkEntityEvent<T: Resource> {
     pub data: T,
 }
 
+// an event serialized when it's sent, so entity references are mapped with the latest entities
+pub(crate) type NetworkDeferredEvent = Box<dyn FnOnce() -> NetworkSerializedStruct + Send + Sync>;
+
 pub trait NetworkEventTraits: Resource + Serialize + DeserializeOwned {}
 impl<T> NetworkEventTraits for T where T: Resource + Serialize + DeserializeOwned {}
@@ -0,0 +1,45 @@
+use crate::entity::NetworkEntity;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// orders entities so every parent comes before its children
+pub(crate) fn parents_first(
+    parents: &HashMap<NetworkEntity, Option<NetworkEntity>>,
+) -> Vec<NetworkEntity> {
+    let depth = |mut entity: NetworkEntity| {
+        let mut depth = 0;
+        // the depth is capped in case of a cycle
+        while let Some(Some(parent)) = parents.get(&entity) {
+            if depth >= parents.len() {
+                break;
+            }
+            entity = *parent;
+            depth += 1;
+        }
+        depth
+    };
+    let mut entities: Vec<NetworkEntity> = parents.keys().copied().collect();
+    entities.sort_by_cached_key(|entity| depth(*entity));
+    entities
+}
+
+pub(crate) fn set_local_parent(world: &mut World, child: Entity, parent: Option<Entity>) {
+    if world.get_entity(child).is_none() {
+        return;
+    }
+    if let Some(Parent(old_parent)) = world.get::<Parent>(child).copied() {
+        if Some(old_parent) == parent {
+            return;
+        }
+        if let Some(mut old_parent) = world.get_entity_mut(old_parent) {
+            old_parent.remove_children(&[child]);
+        } else {
+            world.entity_mut(child).remove::<Parent>();
+        }
+    }
+    if let Some(parent) = parent {
+        if let Some(mut parent) = world.get_entity_mut(parent) {
+            parent.push_children(&[child]);
+        }
+    }
+}
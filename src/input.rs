@@ -0,0 +1,151 @@
+use crate::{
+    network_type_name::NetworkTypeName, player::NetworkPlayer,
+    serialized_struct::NetworkSerializedStruct,
+};
+use bevy::{ecs::system::Resource, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+// how many past ticks of input are resent with every new input
+pub(crate) const INPUT_REDUNDANCY: usize = 3;
+// inputs for ticks this far from the server tick are dropped, so a client with a bad clock or a
+// made up tick can't grow the jitter buffer forever
+pub(crate) const INPUT_WINDOW: u64 = 256;
+
+pub trait NetworkInputTraits: Resource + Serialize + DeserializeOwned + Clone {}
+impl<T> NetworkInputTraits for T where T: Resource + Serialize + DeserializeOwned + Clone {}
+
+pub struct NetworkInput<T> {
+    pub data: T,
+    // true when no input arrived in time for this tick and the last input was reused
+    pub repeated: bool,
+}
+
+pub struct NetworkInputs<T> {
+    tick: u64,
+    inputs: HashMap<NetworkPlayer, NetworkInput<T>>,
+}
+
+impl<T> Default for NetworkInputs<T> {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            inputs: HashMap::new(),
+        }
+    }
+}
+
+impl<T> NetworkInputs<T> {
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get(&self, player: NetworkPlayer) -> Option<&T> {
+        self.inputs.get(&player).map(|input| &input.data)
+    }
+
+    pub fn get_input(&self, player: NetworkPlayer) -> Option<&NetworkInput<T>> {
+        self.inputs.get(&player)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&NetworkPlayer, &NetworkInput<T>)> {
+        self.inputs.iter()
+    }
+}
+
+// inputs that the client has already sent, kept around to be resent
+#[derive(Default)]
+pub(crate) struct NetworkInputHistory {
+    last_tick: Option<u64>,
+    inputs: VecDeque<(u64, NetworkSerializedStruct)>,
+}
+
+impl NetworkInputHistory {
+    pub(crate) fn push(
+        &mut self,
+        tick: u64,
+        input: NetworkSerializedStruct,
+    ) -> Vec<(u64, NetworkSerializedStruct)> {
+        // inputs must be for increasing ticks, even if the clock estimate moves backwards
+        let tick = match self.last_tick {
+            Some(last_tick) if tick <= last_tick => last_tick + 1,
+            _ => tick,
+        };
+        self.last_tick = Some(tick);
+        self.inputs.push_back((tick, input));
+        while self.inputs.len() > INPUT_REDUNDANCY {
+            self.inputs.pop_front();
+        }
+        self.inputs.iter().cloned().collect()
+    }
+}
+
+// server side jitter buffer for one input type of one player
+#[derive(Default)]
+pub(crate) struct NetworkInputBuffer {
+    consumed_tick: Option<u64>,
+    pending: BTreeMap<u64, NetworkSerializedStruct>,
+    last: Option<NetworkSerializedStruct>,
+}
+
+impl NetworkInputBuffer {
+    pub(crate) fn receive(&mut self, tick: u64, input: NetworkSerializedStruct, current_tick: u64) {
+        if tick + INPUT_WINDOW <= current_tick || tick >= current_tick + INPUT_WINDOW {
+            return;
+        }
+        if let Some(consumed_tick) = self.consumed_tick {
+            if tick <= consumed_tick {
+                return;
+            }
+        }
+        self.pending.entry(tick).or_insert(input);
+    }
+
+    // takes the newest input that is due, falling back to the last input
+    pub(crate) fn consume(&mut self, tick: u64) -> Option<(NetworkSerializedStruct, bool)> {
+        let due: Vec<u64> = self.pending.range(..=tick).map(|(tick, _)| *tick).collect();
+        if let Some(newest) = due.last() {
+            self.consumed_tick = Some(*newest);
+            let input = self.pending.remove(newest).unwrap();
+            for tick in due.iter() {
+                self.pending.remove(tick);
+            }
+            self.last = Some(input.clone());
+            Some((input, false))
+        } else {
+            self.last.clone().map(|input| (input, true))
+        }
+    }
+}
+
+// every player's input for a tick, and whether it's repeated from an earlier tick
+type NetworkTickInputs = Vec<(NetworkPlayer, NetworkSerializedStruct, bool)>;
+type NetworkInputSendToWorldFn = Box<dyn Fn(&mut World, u64, NetworkTickInputs) + Send + Sync>;
+
+pub struct NetworkRegistryInput {
+    pub(crate) send_to_world: NetworkInputSendToWorldFn,
+}
+
+impl NetworkRegistryInput {
+    pub(crate) fn new<T>() -> Self
+    where
+        T: NetworkInputTraits,
+    {
+        Self {
+            send_to_world: Box::new(|world: &mut World, tick: u64, inputs: NetworkTickInputs| {
+                let mut network_inputs = world.get_resource_mut::<NetworkInputs<T>>().unwrap();
+                network_inputs.tick = tick;
+                network_inputs.inputs.clear();
+                for (player, input, repeated) in inputs {
+                    if let Some(data) = input.to_struct::<T>() {
+                        network_inputs
+                            .inputs
+                            .insert(player, NetworkInput { data, repeated });
+                    }
+                }
+            }),
+        }
+    }
+}
+
+pub(crate) type NetworkInputBuffers = HashMap<NetworkTypeName, NetworkInputBuffer>;
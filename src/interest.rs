@@ -0,0 +1,26 @@
+// entities are relevant to a player when they're close to the player's viewpoint entity,
+// positions are read from the GlobalTransform of network entities, so children are placed
+// where their parent puts them
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct NetworkSpatialInterest {
+    pub radius: f32,
+    // entities only leave once they're this far past the radius, so they don't flicker at the edge
+    pub hysteresis: f32,
+}
+
+impl NetworkSpatialInterest {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            hysteresis: radius * 0.1,
+        }
+    }
+
+    pub(crate) fn in_range(&self, distance: f32, was_in_range: bool) -> bool {
+        if was_in_range {
+            distance <= self.radius + self.hysteresis
+        } else {
+            distance <= self.radius
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    entity::NetworkEntity, network::Network, network_type_name::NetworkTypeName,
+    registry::NetworkRegistry,
+};
+use bevy::prelude::*;
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+};
+
+// how many ticks of history are kept for each entity
+const DEFAULT_HISTORY_LENGTH: u64 = 128;
+
+pub trait NetworkLagCompensationTraits: Component + Clone + PartialEq {}
+impl<T> NetworkLagCompensationTraits for T where T: Component + Clone + PartialEq {}
+
+struct NetworkComponentHistory<T> {
+    entities: HashMap<NetworkEntity, VecDeque<(u64, T)>>,
+    rewound: Vec<(Entity, T)>,
+}
+
+impl<T> Default for NetworkComponentHistory<T> {
+    fn default() -> Self {
+        Self {
+            entities: HashMap::new(),
+            rewound: vec![],
+        }
+    }
+}
+
+impl<T> NetworkComponentHistory<T> {
+    fn get(&self, entity: NetworkEntity, tick: u64) -> Option<&T> {
+        if let Some(history) = self.entities.get(&entity) {
+            history
+                .iter()
+                .rev()
+                .find(|(recorded_tick, _)| *recorded_tick <= tick)
+                .map(|(_, value)| value)
+        } else {
+            None
+        }
+    }
+
+    fn prune(&mut self, oldest_tick: u64) {
+        for history in self.entities.values_mut() {
+            while let Some((tick, _)) = history.front() {
+                if *tick < oldest_tick {
+                    history.pop_front();
+                } else {
+                    break;
+                }
+            }
+        }
+        self.entities.retain(|_, history| !history.is_empty());
+    }
+}
+
+pub(crate) struct NetworkLagCompensation {
+    history_length: u64,
+    times: VecDeque<(u64, f64)>,
+    histories: HashMap<NetworkTypeName, Box<dyn Any + Send + Sync>>,
+    rewound_tick: Option<u64>,
+}
+
+impl Default for NetworkLagCompensation {
+    fn default() -> Self {
+        Self {
+            history_length: DEFAULT_HISTORY_LENGTH,
+            times: VecDeque::new(),
+            histories: HashMap::new(),
+            rewound_tick: None,
+        }
+    }
+}
+
+impl NetworkLagCompensation {
+    fn history<T>(&self) -> Option<&NetworkComponentHistory<T>>
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        self.histories
+            .get(&NetworkTypeName::of::<T>())
+            .and_then(|history| history.downcast_ref::<NetworkComponentHistory<T>>())
+    }
+
+    fn history_mut<T>(&mut self) -> &mut NetworkComponentHistory<T>
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        self.histories
+            .entry(NetworkTypeName::of::<T>())
+            .or_insert_with(|| Box::new(NetworkComponentHistory::<T>::default()))
+            .downcast_mut::<NetworkComponentHistory<T>>()
+            .unwrap()
+    }
+
+    pub(crate) fn set_history_length(&mut self, ticks: u64) {
+        self.history_length = ticks.max(1);
+    }
+
+    pub(crate) fn oldest_tick(&self) -> Option<u64> {
+        self.times.front().map(|(tick, _)| *tick)
+    }
+
+    pub(crate) fn record_time(&mut self, tick: u64, time: f64) {
+        self.times.push_back((tick, time));
+        while self.times.len() as u64 > self.history_length {
+            self.times.pop_front();
+        }
+    }
+
+    // the latest recorded tick that happened at or before the given server time
+    pub(crate) fn tick_at_time(&self, time: f64) -> Option<u64> {
+        self.times
+            .iter()
+            .rev()
+            .find(|(_, recorded_time)| *recorded_time <= time)
+            .or_else(|| self.times.front())
+            .map(|(tick, _)| *tick)
+    }
+
+    pub(crate) fn get<T>(&self, entity: NetworkEntity, tick: u64) -> Option<&T>
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        self.history::<T>()
+            .and_then(|history| history.get(entity, tick))
+    }
+
+    fn record<T>(&mut self, world: &mut World, tick: u64)
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        let oldest_tick = self.oldest_tick().unwrap_or(tick);
+        let history = self.history_mut::<T>();
+        let mut query = world.query::<(&NetworkEntity, &T)>();
+        for (network_entity, component) in query.iter(world) {
+            history
+                .entities
+                .entry(*network_entity)
+                .or_default()
+                .push_back((tick, component.clone()));
+        }
+        history.prune(oldest_tick);
+    }
+
+    fn rewind<T>(&mut self, world: &mut World, tick: u64)
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        let history = self.history_mut::<T>();
+        // only components that differ are written, so unchanged ones aren't marked as changed
+        let mut query = world.query::<(Entity, &NetworkEntity, &T)>();
+        let rewound: Vec<(Entity, T)> = query
+            .iter(world)
+            .filter_map(|(entity, network_entity, component)| {
+                history
+                    .get(*network_entity, tick)
+                    .filter(|value| *value != component)
+                    .map(|value| (entity, value.clone()))
+            })
+            .collect();
+        for (entity, value) in rewound {
+            let mut component = world.get_mut::<T>(entity).unwrap();
+            history
+                .rewound
+                .push((entity, std::mem::replace(&mut *component, value)));
+        }
+    }
+
+    fn restore<T>(&mut self, world: &mut World)
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        let history = self.history_mut::<T>();
+        for (entity, value) in history.rewound.drain(..) {
+            if let Some(mut component) = world.get_mut::<T>(entity) {
+                if *component != value {
+                    *component = value;
+                }
+            }
+        }
+    }
+}
+
+type NetworkLagCompensationTickFn =
+    Box<dyn Fn(&mut World, &mut NetworkLagCompensation, u64) + Send + Sync>;
+type NetworkLagCompensationRestoreFn =
+    Box<dyn Fn(&mut World, &mut NetworkLagCompensation) + Send + Sync>;
+
+pub struct NetworkRegistryLagCompensation {
+    pub(crate) record: NetworkLagCompensationTickFn,
+    pub(crate) rewind: NetworkLagCompensationTickFn,
+    pub(crate) restore: NetworkLagCompensationRestoreFn,
+}
+
+impl NetworkRegistryLagCompensation {
+    pub(crate) fn new<T>() -> Self
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        Self {
+            record: Box::new(
+                |world: &mut World, lag_compensation: &mut NetworkLagCompensation, tick: u64| {
+                    lag_compensation.record::<T>(world, tick);
+                },
+            ),
+            rewind: Box::new(
+                |world: &mut World, lag_compensation: &mut NetworkLagCompensation, tick: u64| {
+                    lag_compensation.rewind::<T>(world, tick);
+                },
+            ),
+            restore: Box::new(
+                |world: &mut World, lag_compensation: &mut NetworkLagCompensation| {
+                    lag_compensation.restore::<T>(world);
+                },
+            ),
+        }
+    }
+}
+
+pub(crate) fn record_history(
+    lag_compensation: &mut NetworkLagCompensation,
+    registry: &NetworkRegistry,
+    world: &mut World,
+    tick: u64,
+    time: f64,
+) {
+    lag_compensation.record_time(tick, time);
+    for (_, entry) in registry.entries() {
+        if let Some(entry) = &entry.lag_compensation {
+            (entry.record)(world, lag_compensation, tick);
+        }
+    }
+}
+
+pub trait NetworkRewind {
+    fn network_rewind(&mut self, tick: u64);
+    fn network_restore(&mut self);
+    fn with_network_rewind<F>(&mut self, tick: u64, f: F)
+    where
+        F: FnOnce(&mut World);
+}
+
+impl NetworkRewind for World {
+    fn network_rewind(&mut self, tick: u64) {
+        self.network_restore();
+        self.resource_scope(|world, mut network: Mut<Network>| {
+            let Network {
+                registry, state, ..
+            } = &mut *network;
+            if let Some(server) = state.server_mut() {
+                for (_, entry) in registry.entries() {
+                    if let Some(entry) = &entry.lag_compensation {
+                        (entry.rewind)(world, &mut server.lag_compensation, tick);
+                    }
+                }
+                server.lag_compensation.rewound_tick = Some(tick);
+            }
+        });
+    }
+
+    fn network_restore(&mut self) {
+        self.resource_scope(|world, mut network: Mut<Network>| {
+            let Network {
+                registry, state, ..
+            } = &mut *network;
+            if let Some(server) = state.server_mut() {
+                if server.lag_compensation.rewound_tick.take().is_some() {
+                    for (_, entry) in registry.entries() {
+                        if let Some(entry) = &entry.lag_compensation {
+                            (entry.restore)(world, &mut server.lag_compensation);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn with_network_rewind<F>(&mut self, tick: u64, f: F)
+    where
+        F: FnOnce(&mut World),
+    {
+        self.network_rewind(tick);
+        f(self);
+        self.network_restore();
+    }
+}
@@ -1,20 +1,33 @@
 mod add_network_data;
+mod bandwidth;
 mod client;
+mod clock;
 mod entity;
+mod entity_map;
 mod event_queue;
 mod events;
+mod hierarchy;
+mod input;
+mod interest;
 mod internal_protocol;
+mod lag_compensation;
+mod lockstep;
 mod messages;
 mod network;
 mod network_type_name;
 mod player;
 mod player_data;
 mod plugin;
+mod prefab;
 mod registry;
 mod relevancy;
+mod resource;
+mod rollback;
 mod serialized_struct;
 mod serializer;
 mod server;
+mod state;
+mod update_rate;
 
 #[cfg(test)]
 mod tests;
@@ -22,15 +35,38 @@ mod tests;
 pub mod prelude {
     pub use super::{
         add_network_data::AddNetworkData,
+        bandwidth::{NetworkBandwidthStats, NetworkPriority},
         client::NetworkClient,
-        entity::{NetworkEntity, NetworkEntityOwner},
+        clock::NetworkClock,
+        entity::{
+            NetworkDelayDespawn, NetworkDespawned, NetworkEntity, NetworkEntityOwner,
+            NetworkOwnerLeavePolicy, NetworkOwnershipPolicy,
+        },
+        entity_map::{network_entity, network_entity_option, NetworkEntities},
         events::{
-            NetworkConnectEvent, NetworkConnectingEvent, NetworkDisconnectEvent, NetworkEvent,
-            NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent, NetworkServerEvent,
+            NetworkConnectEvent, NetworkConnectingEvent, NetworkDesyncEvent,
+            NetworkDisconnectEvent, NetworkEntityDespawnEvent, NetworkEntityDespawnReason,
+            NetworkEntityEventDroppedEvent, NetworkEntityOrphanedEvent,
+            NetworkEntityOwnershipChangedEvent, NetworkEntitySpawnEvent, NetworkEvent,
+            NetworkOwnershipRequestEvent, NetworkOwnershipResponseEvent, NetworkPlayerJoinEvent,
+            NetworkPlayerLeaveEvent, NetworkServerEvent, NetworkSpawnRequestEvent,
+            NetworkSpawnResponseEvent, NetworkSyncTestMismatchEvent,
         },
+        input::{NetworkInput, NetworkInputs},
+        interest::NetworkSpatialInterest,
+        lag_compensation::NetworkRewind,
+        lockstep::NetworkLockstepTickEvent,
         network::Network,
+        network_type_name::NetworkTypeName,
         player::NetworkPlayer,
-        plugin::NetworkPlugin,
+        plugin::{NetworkPlugin, NetworkStage, NetworkSystem},
+        relevancy::{
+            NetworkDefaultRelevancy, NetworkRelevancyRule, NetworkRelevancyRuleMode,
+            NetworkVisibility,
+        },
+        rollback::{NetworkRollbackInput, NetworkRollbackInputs},
         server::NetworkServer,
+        state::NetworkStateRequestEvent,
+        update_rate::NetworkUpdateRate,
     };
 }
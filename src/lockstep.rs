@@ -0,0 +1,229 @@
+use crate::{
+    input::NetworkInputTraits, network_type_name::NetworkTypeName, player::NetworkPlayer,
+    serialized_struct::NetworkSerializedStruct,
+};
+use bevy::{app::Events, prelude::*};
+use std::collections::{BTreeMap, HashMap};
+
+pub(crate) const DEFAULT_INPUT_DELAY: u64 = 2;
+// inputs and checksums this many ticks away from the current lockstep tick are dropped, so a player
+// that runs ahead or never reports a tick can't grow them forever
+pub(crate) const LOCKSTEP_WINDOW: u64 = 256;
+
+pub trait NetworkLockstepTraits: NetworkInputTraits + Default {}
+impl<T> NetworkLockstepTraits for T where T: NetworkInputTraits + Default {}
+
+pub struct NetworkLockstepTickEvent<T> {
+    pub tick: u64,
+    pub inputs: Vec<(NetworkPlayer, T)>,
+}
+
+// client side bookkeeping for one lockstep input type
+#[derive(Default)]
+pub(crate) struct NetworkLockstepClient {
+    pub(crate) start_tick: u64,
+    pub(crate) next_input_tick: Option<u64>,
+}
+
+impl NetworkLockstepClient {
+    pub(crate) fn start(&mut self, tick: u64) {
+        self.start_tick = tick;
+        if let Some(next_input_tick) = self.next_input_tick {
+            if next_input_tick < tick {
+                self.next_input_tick = None;
+            }
+        }
+    }
+}
+
+// server side bookkeeping for one lockstep input type
+#[derive(Default)]
+pub(crate) struct NetworkLockstepServer {
+    next_tick: u64,
+    // the first tick each player takes part in
+    participants: HashMap<NetworkPlayer, u64>,
+    inputs: BTreeMap<u64, HashMap<NetworkPlayer, NetworkSerializedStruct>>,
+}
+
+impl NetworkLockstepServer {
+    pub(crate) fn receive(
+        &mut self,
+        player: NetworkPlayer,
+        tick: u64,
+        input: NetworkSerializedStruct,
+    ) {
+        if tick < self.next_tick || tick >= self.next_tick + LOCKSTEP_WINDOW {
+            return;
+        }
+        self.inputs.entry(tick).or_default().insert(player, input);
+    }
+
+    pub(crate) fn next_tick(&self) -> u64 {
+        self.next_tick
+    }
+
+    // players that haven't been seen yet take part starting with the next tick
+    pub(crate) fn add_player(&mut self, player: NetworkPlayer) -> Option<u64> {
+        if self.participants.contains_key(&player) {
+            None
+        } else {
+            self.participants.insert(player, self.next_tick);
+            Some(self.next_tick)
+        }
+    }
+
+    pub(crate) fn remove_player(&mut self, player: NetworkPlayer) {
+        self.participants.remove(&player);
+        for inputs in self.inputs.values_mut() {
+            inputs.remove(&player);
+        }
+        self.inputs.retain(|_, inputs| !inputs.is_empty());
+    }
+
+    pub(crate) fn participants(&self, tick: u64) -> Vec<NetworkPlayer> {
+        self.participants
+            .iter()
+            .filter(|(_, first_tick)| **first_tick <= tick)
+            .map(|(player, _)| *player)
+            .collect()
+    }
+
+    // pops the next tick if every participating player has sent their input for it
+    pub(crate) fn advance(
+        &mut self,
+    ) -> Option<(u64, Vec<(NetworkPlayer, NetworkSerializedStruct)>)> {
+        let tick = self.next_tick;
+        let participants = self.participants(tick);
+        if participants.is_empty() {
+            return None;
+        }
+        let ready = if let Some(inputs) = self.inputs.get(&tick) {
+            participants
+                .iter()
+                .all(|player| inputs.contains_key(player))
+        } else {
+            false
+        };
+        if ready {
+            let inputs = self.inputs.remove(&tick).unwrap();
+            self.next_tick += 1;
+            let mut inputs: Vec<(NetworkPlayer, NetworkSerializedStruct)> = inputs
+                .into_iter()
+                .filter(|(player, _)| participants.contains(player))
+                .collect();
+            inputs.sort_by_key(|(player, _)| player.0);
+            Some((tick, inputs))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NetworkLockstepChecksums {
+    checksums: BTreeMap<u64, HashMap<NetworkPlayer, u64>>,
+}
+
+impl NetworkLockstepChecksums {
+    pub(crate) fn receive(
+        &mut self,
+        player: NetworkPlayer,
+        tick: u64,
+        checksum: u64,
+        current_tick: u64,
+    ) {
+        if tick >= current_tick + LOCKSTEP_WINDOW {
+            return;
+        }
+        self.checksums
+            .entry(tick)
+            .or_default()
+            .insert(player, checksum);
+    }
+
+    pub(crate) fn remove_player(&mut self, player: NetworkPlayer) {
+        for checksums in self.checksums.values_mut() {
+            checksums.remove(&player);
+        }
+        self.checksums.retain(|_, checksums| !checksums.is_empty());
+    }
+
+    // returns the checksums of every tick that all players reported and that didn't match,
+    // ticks that fell out of the window without every player reporting them are dropped
+    pub(crate) fn compare<F>(
+        &mut self,
+        players: F,
+        current_tick: u64,
+    ) -> Vec<(u64, Vec<(NetworkPlayer, u64)>)>
+    where
+        F: Fn(u64) -> Vec<NetworkPlayer>,
+    {
+        self.checksums
+            .retain(|tick, _| tick + LOCKSTEP_WINDOW > current_tick);
+        let mut desyncs = vec![];
+        let complete: Vec<(u64, Vec<NetworkPlayer>)> = self
+            .checksums
+            .iter()
+            .map(|(tick, checksums)| (*tick, checksums, players(*tick)))
+            .filter(|(_, checksums, players)| players.iter().all(|p| checksums.contains_key(p)))
+            .map(|(tick, _, players)| (tick, players))
+            .collect();
+        for (tick, players) in complete {
+            let checksums = self.checksums.remove(&tick).unwrap();
+            let mut checksums: Vec<(NetworkPlayer, u64)> = checksums
+                .into_iter()
+                .filter(|(player, _)| players.contains(player))
+                .collect();
+            checksums.sort_by_key(|(player, _)| player.0);
+            if checksums.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+                desyncs.push((tick, checksums));
+            }
+        }
+        desyncs
+    }
+}
+
+pub(crate) type NetworkLockstepServers = HashMap<NetworkTypeName, NetworkLockstepServer>;
+
+// the furthest any lockstep input type got
+pub(crate) fn current_lockstep_tick(lockstep: &NetworkLockstepServers) -> u64 {
+    lockstep
+        .values()
+        .map(|lockstep| lockstep.next_tick())
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) type NetworkLockstepInputs = Vec<(NetworkPlayer, NetworkSerializedStruct)>;
+type NetworkLockstepSendToWorldFn =
+    Box<dyn Fn(&mut World, u64, NetworkLockstepInputs) + Send + Sync>;
+
+pub struct NetworkRegistryLockstep {
+    pub(crate) send_to_world: NetworkLockstepSendToWorldFn,
+}
+
+impl NetworkRegistryLockstep {
+    pub(crate) fn new<T>() -> Self
+    where
+        T: NetworkLockstepTraits,
+    {
+        Self {
+            send_to_world: Box::new(
+                |world: &mut World, tick: u64, inputs: NetworkLockstepInputs| {
+                    let mut events = world
+                        .get_resource_mut::<Events<NetworkLockstepTickEvent<T>>>()
+                        .unwrap();
+                    events.send(NetworkLockstepTickEvent {
+                        tick,
+                        inputs: inputs
+                            .into_iter()
+                            .filter_map(|(player, input)| {
+                                input.to_struct::<T>().map(|input| (player, input))
+                            })
+                            .collect(),
+                    });
+                },
+            ),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{
     entity::NetworkEntity,
+    events::NetworkEntityDespawnReason,
+    network_type_name::NetworkTypeName,
     player::NetworkPlayer,
     serialized_struct::{NetworkSerializedStruct, NetworkSerializedStructMap},
     serializer::{deserialize, serialize},
 };
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum NetworkMessage {
@@ -25,19 +28,103 @@ pub enum NetworkMessage {
     },
     EntitySpawn {
         entity: NetworkEntity,
+        owner: Option<NetworkPlayer>,
+        prefab: Option<NetworkSerializedStruct>,
+        parent: Option<NetworkEntity>,
     },
     EntityDespawn {
         entity: NetworkEntity,
+        reason: NetworkEntityDespawnReason,
     },
     EntityOwner {
         entity: NetworkEntity,
-        owner: bool,
+        owner: Option<NetworkPlayer>,
+    },
+    EntityParent {
+        entity: NetworkEntity,
+        parent: Option<NetworkEntity>,
+    },
+    EntitySpawnRequest {
+        entity: NetworkEntity,
+        prefab: Option<NetworkSerializedStruct>,
+    },
+    EntitySpawnResponse {
+        requested: NetworkEntity,
+        confirmed: Option<NetworkEntity>,
+    },
+    EntityOwnershipRequest {
+        entity: NetworkEntity,
+        release: bool,
+    },
+    EntityOwnershipResponse {
+        entity: NetworkEntity,
+        release: bool,
+        approved: bool,
     },
     EntityEvent {
         entity: NetworkEntity,
         from: Option<NetworkPlayer>,
         data: NetworkSerializedStruct,
     },
+    ClockRequest {
+        client_time: f64,
+        rtt: f64,
+    },
+    ClockResponse {
+        client_time: f64,
+        server_time: f64,
+        server_tick: u64,
+    },
+    InterpolationDelay {
+        seconds: f64,
+    },
+    Input {
+        inputs: Vec<(u64, NetworkSerializedStruct)>,
+    },
+    LockstepInput {
+        tick: u64,
+        data: NetworkSerializedStruct,
+    },
+    LockstepTick {
+        tick: u64,
+        type_name: NetworkTypeName,
+        inputs: Vec<(NetworkPlayer, NetworkSerializedStruct)>,
+    },
+    RollbackInput {
+        tick: u64,
+        data: NetworkSerializedStruct,
+    },
+    RollbackRemoteInput {
+        player: NetworkPlayer,
+        tick: u64,
+        data: NetworkSerializedStruct,
+    },
+    RollbackStart {
+        type_name: NetworkTypeName,
+        player: NetworkPlayer,
+        tick: u64,
+    },
+    Resource {
+        data: NetworkSerializedStruct,
+    },
+    ResourceRemove {
+        type_name: NetworkTypeName,
+    },
+    StateRequest {
+        data: NetworkSerializedStruct,
+    },
+    LockstepStart {
+        type_name: NetworkTypeName,
+        tick: u64,
+    },
+    LockstepChecksum {
+        tick: u64,
+        checksum: u64,
+    },
+    LockstepDesync {
+        tick: u64,
+        checksums: Vec<(NetworkPlayer, u64)>,
+    },
 }
 
 impl NetworkMessage {
@@ -47,4 +134,8 @@ impl NetworkMessage {
     pub fn serialize(&self) -> String {
         serialize(&self)
     }
+    // for messages sent to many players, encoded once and shared by every socket
+    pub fn serialize_shared(&self) -> Arc<str> {
+        self.serialize().into()
+    }
 }
@@ -1,23 +1,41 @@
 use crate::{
+    bandwidth::{update_priority, NetworkBandwidth, NetworkPriority},
     client::{NetworkClient, NetworkClientEntity, NetworkClientPlayer},
-    entity::{NetworkEntity, NetworkEntityOwner},
+    clock::NetworkClock,
+    entity::{
+        NetworkDelayDespawn, NetworkDespawned, NetworkEntity, NetworkEntityOwner,
+        NetworkOwnerLeavePolicy, NetworkOwnershipPolicy,
+    },
+    entity_map::{remove_network_entities, NetworkEntities, NetworkEntityMap},
     event_queue::EventQueue,
     events::{
-        NetworkConnectEvent, NetworkConnectingEvent, NetworkDisconnectEvent,
-        NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkConnectEvent, NetworkConnectingEvent, NetworkDesyncEvent, NetworkDisconnectEvent,
+        NetworkEntityDespawnEvent, NetworkEntityDespawnReason, NetworkEntityOrphanedEvent,
+        NetworkEntitySpawnEvent, NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkSpawnRequestEvent, NetworkSpawnResponseEvent,
     },
+    hierarchy::{parents_first, set_local_parent},
+    input::NetworkInputBuffers,
     internal_protocol::InternalHost,
+    lag_compensation::record_history,
+    lockstep::current_lockstep_tick,
     messages::NetworkMessage,
     player::NetworkPlayer,
     player_data::NetworkPlayerDataTraits,
+    prefab::prefab_payload,
     registry::NetworkRegistry,
-    relevancy::NetworkRelevancyState,
+    relevancy::{
+        NetworkDefaultRelevancy, NetworkRelevancy, NetworkRelevancyState, NetworkRuleRelevancy,
+        NetworkVisibility,
+    },
+    rollback::NetworkRollbackSession,
     serialized_struct::NetworkSerializedStructMap,
-    server::{NetworkServer, NetworkServerJoiner, NetworkServerPlayer},
+    server::{NetworkServer, NetworkServerJoiner, NetworkServerPlayer, PENDING_TIMEOUT_TICKS},
+    update_rate::NetworkUpdateRate,
 };
 use bevy::prelude::*;
 use bevy_nety_protocol::{NetworkConnectStatus, NetworkConnector, NetworkHost};
-use std::any::type_name;
+use std::{any::type_name, collections::HashMap, sync::Arc};
 
 pub enum NetworkState {
     Connected {
@@ -30,6 +48,22 @@ pub enum NetworkState {
     Disconnected,
 }
 
+impl NetworkState {
+    pub(crate) fn server_mut(&mut self) -> Option<&mut NetworkServer> {
+        match self {
+            NetworkState::Connected { server, .. } => server.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn client_mut(&mut self) -> Option<&mut NetworkClient> {
+        match self {
+            NetworkState::Connected { client, .. } => client.as_mut(),
+            _ => None,
+        }
+    }
+}
+
 impl Default for NetworkState {
     fn default() -> Self {
         Self::Disconnected
@@ -38,7 +72,7 @@ impl Default for NetworkState {
 
 #[derive(Default)]
 pub struct Network {
-    state: NetworkState,
+    pub(crate) state: NetworkState,
     event_queue: EventQueue,
     pub(crate) registry: NetworkRegistry,
     my_player_data: NetworkSerializedStructMap,
@@ -139,10 +173,7 @@ impl Network {
     }
 
     pub fn server_mut(&mut self) -> Option<&mut NetworkServer> {
-        match &mut self.state {
-            NetworkState::Connected { server, .. } => server.as_mut(),
-            _ => None,
-        }
+        self.state.server_mut()
     }
 
     pub fn client(&self) -> Option<&NetworkClient> {
@@ -153,10 +184,7 @@ impl Network {
     }
 
     pub fn client_mut(&mut self) -> Option<&mut NetworkClient> {
-        match &mut self.state {
-            NetworkState::Connected { client, .. } => client.as_mut(),
-            _ => None,
-        }
+        self.state.client_mut()
     }
 
     pub fn me(&self) -> Option<NetworkPlayer> {
@@ -182,6 +210,27 @@ impl Network {
         }
     }
 
+    pub fn entity_owner(&self, entity: NetworkEntity) -> Option<NetworkPlayer> {
+        match &self.state {
+            NetworkState::Connected { server, client } => {
+                if let Some(server) = server {
+                    server.entity_owner(entity)
+                } else if let Some(client) = client {
+                    client.entity_owner(entity)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // how many network updates entity events wait for their entity to be spawned, before
+    // they're dropped with a NetworkEntityEventDroppedEvent
+    pub fn set_entity_event_buffer_ticks(&mut self, ticks: u64) {
+        self.event_queue.entity_event_buffer.ticks = ticks;
+    }
+
     pub fn set_my_player_data<T>(&mut self, data: T)
     where
         T: NetworkPlayerDataTraits,
@@ -285,21 +334,57 @@ macro_rules! get_client_from_state {
 pub fn update_network(world: &mut World) {
     let unsafe_world = unsafe { &mut *(world as *mut World) };
     let mut network = unsafe_world.get_resource_mut::<Network>().unwrap();
+    update_entity_map(world);
     update_connector(&mut network);
+    server_record_history(&mut network, world);
+    server_advance_tick(&mut network);
     client_initialize(&mut network);
     server_entities_diff(&mut network, world);
+    send_deferred_events(&mut network, world);
     entity_owner_send_events(&mut network, world);
     server_accept_sockets(&mut network);
+    client_check_states(&mut network, world);
     client_receive_messages(&mut network);
     server_receive_messages_from_joiners(&mut network);
     server_initialize_players(&mut network);
+    server_send_resources(&mut network, world);
     server_receive_messages_from_players(&mut network);
+    server_expire_spawn_requests(&mut network);
+    server_consume_inputs(&mut network, world);
+    server_advance_lockstep(&mut network);
+    server_start_rollback(&mut network);
     client_check_disconnect(&mut network);
     server_check_disconnects(&mut network);
+    client_clock_sync(&mut network);
     client_spawn_despawn_entities(&mut network, world);
+    client_request_spawns(&mut network, world);
     send_events(&mut network, world);
+    update_clock(&mut network, world);
     update_entities(&mut network, world);
+    despawn_released_entities(world);
     server_send_entity_events(&mut network);
+    server_send_entity_updates(&mut network, world);
+    update_entity_map(world);
+}
+
+// components removed after the network update are forgotten when the frame ends, so they're
+// picked up here, after every user system has run
+pub fn track_removals(world: &mut World) {
+    remove_network_entities(world);
+    world.resource_scope(|world, mut network: Mut<Network>| {
+        if let Some(server) = network.server_mut() {
+            mark_removed_visibility(&mut server.relevancy, world);
+        }
+    });
+}
+
+// entities that lost their NetworkVisibility fall back to the default relevancy
+fn mark_removed_visibility(relevancy: &mut NetworkRelevancy, world: &World) {
+    for entity in world.removed::<NetworkVisibility>() {
+        if let Some(network_entity) = world.get::<NetworkEntity>(entity) {
+            relevancy.set_entity_dirty(*network_entity);
+        }
+    }
 }
 
 fn update_connector(mut network: &mut Network) {
@@ -349,42 +434,192 @@ fn client_initialize(network: &mut Network) {
     }
 }
 
-pub fn server_entities_diff(network: &mut Network, world: &mut World) {
+fn server_record_history(network: &mut Network, world: &mut World) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let server = get_server_from_state!(state);
+    let tick = server.tick;
+    let time = server.time();
+    record_history(&mut server.lag_compensation, registry, world, tick, time);
+}
+
+fn server_advance_tick(network: &mut Network) {
     let Network { state, .. } = network;
     let server = get_server_from_state!(state);
+    server.tick += 1;
+}
+
+fn client_clock_sync(network: &mut Network) {
+    let Network { state, .. } = network;
+    let client = get_client_from_state!(state);
+    client.send_unsynced_inputs();
+    if client.clock.should_request() {
+        let client_time = client.clock.start_request();
+        client.socket.send(
+            NetworkMessage::ClockRequest {
+                client_time,
+                rtt: client.clock.rtt(),
+            }
+            .serialize(),
+        );
+    }
+}
+
+pub fn server_entities_diff(network: &mut Network, world: &mut World) {
+    let Network {
+        state,
+        registry,
+        event_queue,
+        ..
+    } = network;
+    let server = get_server_from_state!(state);
     for (_, entity) in server.entities.iter_mut() {
         entity.exists = false;
     }
-    let mut network_entity_query = world.query::<&NetworkEntity>();
-    for network_entity in network_entity_query.iter(world) {
-        server.get_or_insert_entity(*network_entity).exists = true;
+    let mut network_entity_query = world.query::<(Entity, &NetworkEntity)>();
+    let network_entities: HashMap<Entity, NetworkEntity> = network_entity_query
+        .iter(world)
+        .map(|(entity, network_entity)| (entity, *network_entity))
+        .collect();
+    for (entity, network_entity) in network_entities.iter() {
+        let server_entity = server.get_or_insert_entity(*network_entity);
+        server_entity.exists = true;
+        if !server_entity.initialized {
+            // the spawn payload is captured once, when the entity is first seen
+            server_entity.prefab = prefab_payload(registry, world, *entity);
+            server_entity.initialized = true;
+        }
+        // only parents that are network entities themselves are replicated
+        let parent = world
+            .get::<Parent>(*entity)
+            .and_then(|parent| network_entities.get(&parent.0).copied());
+        if server_entity.parent != parent {
+            server_entity.parent = parent;
+            server_entity.parent_changed = true;
+        }
+    }
+    // the plugin wide default, set by inserting the NetworkDefaultRelevancy resource
+    let default_relevancy = world
+        .get_resource::<NetworkDefaultRelevancy>()
+        .copied()
+        .unwrap_or_default();
+    server.relevancy.set_default_relevancy(default_relevancy);
+    mark_removed_visibility(&mut server.relevancy, world);
+    let mut visibility_query = world.query_filtered::<&NetworkEntity, Changed<NetworkVisibility>>();
+    for network_entity in visibility_query.iter(world) {
+        server.relevancy.set_entity_dirty(*network_entity);
+    }
+    // positions are also used to prioritize updates by distance
+    if server.relevancy.spatial_interest().is_some() || server.relevancy.has_viewpoints() {
+        let mut position_query = world.query::<(&NetworkEntity, &GlobalTransform)>();
+        let positions = position_query
+            .iter(world)
+            .map(|(network_entity, transform)| (*network_entity, transform.translation))
+            .collect();
+        server.relevancy.set_positions(positions);
+    }
+    if server.relevancy.spatial_interest().is_some() {
+        let mut moved_query = world.query_filtered::<&NetworkEntity, Changed<GlobalTransform>>();
+        let moved = moved_query.iter(world).copied().collect();
+        server.relevancy.set_moved(&moved);
     }
+    let mut update_rate_query = world.query::<(&NetworkEntity, &NetworkUpdateRate)>();
+    server.update_rates = update_rate_query
+        .iter(world)
+        .map(|(network_entity, update_rate)| (*network_entity, update_rate.clone()))
+        .collect();
     let NetworkServer {
         players,
         entities,
         local_player,
         relevancy,
+        started,
+        ownership_requests,
+        tick,
+        owner_changes,
         ..
     } = server;
+    let time = started.elapsed().as_secs_f64();
+    let mut orphaned = vec![];
     for (handle, entity) in entities.iter_mut() {
-        if !entity.exists {
+        if !entity.exists && entity.initialized {
+            let message = NetworkMessage::EntityDespawn {
+                entity: *handle,
+                reason: NetworkEntityDespawnReason::Destroyed,
+            }
+            .serialize_shared();
             for player in players.iter_mut() {
                 let is_local_player = if let Some(local_player) = local_player {
                     player.handle == *local_player
                 } else {
                     false
                 };
-                if !is_local_player && relevancy.relevant(player.handle, *handle) {
-                    player
-                        .socket
-                        .send(NetworkMessage::EntityDespawn { entity: *handle }.serialize());
+                if relevancy.relevant(player.handle, *handle) {
+                    player.bandwidth.flush_entity(&mut player.socket, *handle);
+                    if !is_local_player {
+                        player.socket.send_shared(message.clone());
+                    }
+                } else {
+                    player.bandwidth.remove_entity(*handle);
                 }
             }
+            relevancy.remove_entity(*handle);
+            ownership_requests.retain(|(_, entity)| entity != handle);
         }
     }
-    entities.retain(|_, entity| entity.exists);
-    for (handle, network_entity) in entities.iter_mut() {
-        for player in players.iter_mut() {
+    // entities given an owner before they're spawned are kept until they show up in the world,
+    // or until they time out
+    entities.retain(|handle, entity| {
+        let keep = entity.exists
+            || (!entity.initialized && *tick < entity.created + PENDING_TIMEOUT_TICKS);
+        if !keep {
+            relevancy.remove_entity(*handle);
+        }
+        keep
+    });
+    let order = parents_first(
+        &entities
+            .iter()
+            .filter(|(_, entity)| entity.exists)
+            .map(|(handle, entity)| (*handle, entity.parent))
+            .collect(),
+    );
+    let local_entities: HashMap<NetworkEntity, Entity> = network_entities
+        .iter()
+        .map(|(entity, network_entity)| (*network_entity, *entity))
+        .collect();
+    for player in players.iter() {
+        relevancy.track_player(player.handle);
+    }
+    for handle in order.iter() {
+        let network_entity = entities.get_mut(handle).unwrap();
+        let local_entity = local_entities.get(handle).copied();
+        relevancy.track_entity(*handle);
+        // children are evaluated again whenever their parent is
+        if network_entity.owner_changed
+            || network_entity.parent_changed
+            || network_entity
+                .parent
+                .map_or(false, |parent| relevancy.is_entity_dirty(parent))
+        {
+            relevancy.set_entity_dirty(*handle);
+        }
+        let entity_dirty = relevancy.is_entity_dirty(*handle);
+        let evaluated_players = if entity_dirty || relevancy.has_dirty_players() {
+            &mut players[..]
+        } else {
+            &mut []
+        };
+        // encoded the first time a player needs them, then shared with the other players
+        let mut spawn_message: Option<Arc<str>> = None;
+        let mut despawn_message: Option<Arc<str>> = None;
+        let mut owner_message: Option<Arc<str>> = None;
+        let mut parent_message: Option<Arc<str>> = None;
+        for player in evaluated_players.iter_mut() {
+            if !entity_dirty && !relevancy.is_player_dirty(player.handle) {
+                continue;
+            }
             let is_local_player = if let Some(local_player) = local_player {
                 player.handle == *local_player
             } else {
@@ -395,56 +630,123 @@ pub fn server_entities_diff(network: &mut Network, world: &mut World) {
             } else {
                 is_local_player
             };
-            match relevancy.update(player.handle, network_entity, is_owner || is_local_player) {
+            // parents are handled first, so their relevancy is already up to date
+            let inherited = network_entity
+                .parent
+                .filter(|_| network_entity.follow_parent_relevancy)
+                .map(|parent| relevancy.relevant(player.handle, parent));
+            let rules = match local_entity {
+                Some(local_entity) if !registry.relevancy_rules.is_empty() => registry
+                    .relevancy_rules
+                    .evaluate(world, player.handle, local_entity),
+                _ => NetworkRuleRelevancy::default(),
+            };
+            let visibility = local_entity
+                .and_then(|local_entity| world.get::<NetworkVisibility>(local_entity))
+                .and_then(|visibility| visibility.visible(player.handle));
+            match relevancy.update(
+                player.handle,
+                network_entity,
+                is_owner || is_local_player,
+                inherited,
+                rules,
+                visibility,
+            ) {
                 NetworkRelevancyState::Spawn => {
                     if !is_local_player {
-                        player
-                            .socket
-                            .send(NetworkMessage::EntitySpawn { entity: *handle }.serialize());
+                        let message = spawn_message.get_or_insert_with(|| {
+                            NetworkMessage::EntitySpawn {
+                                entity: *handle,
+                                owner: network_entity.owner,
+                                prefab: network_entity.prefab.clone(),
+                                parent: network_entity.parent,
+                            }
+                            .serialize_shared()
+                        });
+                        player.socket.send_shared(message.clone());
                     }
                 }
                 NetworkRelevancyState::Despawn => {
+                    player.bandwidth.flush_entity(&mut player.socket, *handle);
                     if !is_local_player {
-                        player
-                            .socket
-                            .send(NetworkMessage::EntityDespawn { entity: *handle }.serialize());
+                        let message = despawn_message.get_or_insert_with(|| {
+                            NetworkMessage::EntityDespawn {
+                                entity: *handle,
+                                reason: NetworkEntityDespawnReason::Irrelevant,
+                            }
+                            .serialize_shared()
+                        });
+                        player.socket.send_shared(message.clone());
                     }
                 }
-                NetworkRelevancyState::Relevant => {}
-                NetworkRelevancyState::Irrelevant => {}
-            }
-            if network_entity.owner_changed {
-                if let Some(owner) = network_entity.owner {
-                    if owner == player.handle && !is_local_player {
-                        player.socket.send(
+                NetworkRelevancyState::Relevant => {
+                    // players that just had the entity spawned already got the owner with it
+                    if network_entity.owner_changed && !is_local_player {
+                        let message = owner_message.get_or_insert_with(|| {
                             NetworkMessage::EntityOwner {
                                 entity: network_entity.handle,
-                                owner: true,
+                                owner: network_entity.owner,
                             }
-                            .serialize(),
-                        );
+                            .serialize_shared()
+                        });
+                        player.socket.send_shared(message.clone());
                     }
-                }
-                if let Some(last_owner) = network_entity.last_owner {
-                    if last_owner == player.handle && !is_local_player {
-                        player.socket.send(
-                            NetworkMessage::EntityOwner {
+                    if network_entity.parent_changed && !is_local_player {
+                        let message = parent_message.get_or_insert_with(|| {
+                            NetworkMessage::EntityParent {
                                 entity: network_entity.handle,
-                                owner: false,
+                                parent: network_entity.parent,
                             }
-                            .serialize(),
-                        );
+                            .serialize_shared()
+                        });
+                        player.socket.send_shared(message.clone());
                     }
                 }
+                NetworkRelevancyState::Irrelevant => {}
             }
         }
+        network_entity.parent_changed = false;
         if network_entity.owner_changed {
+            if network_entity.last_owner != network_entity.owner {
+                event_queue.entity_ownership_changed(
+                    network_entity.handle,
+                    network_entity.last_owner,
+                    network_entity.owner,
+                );
+            }
             network_entity.last_owner = None;
             network_entity.owner_changed = false;
         }
         if let Some(owner) = network_entity.owner {
             if players.iter().find(|p| p.handle == owner).is_none() {
-                network_entity.owner = None;
+                network_entity.set_owner(None);
+                owner_changes.insert(*handle);
+                if let NetworkOwnerLeavePolicy::Reserve { seconds } =
+                    network_entity.owner_leave_policy
+                {
+                    network_entity.reserved = Some((owner, time + seconds));
+                }
+                orphaned.push((*handle, owner, network_entity.owner_leave_policy));
+            }
+        }
+        if let Some((_, until)) = network_entity.reserved {
+            if time >= until {
+                network_entity.reserved = None;
+            }
+        }
+    }
+    relevancy.clear_dirty();
+    for (network_entity, owner, policy) in orphaned {
+        if let Some(entity) = local_entities.get(&network_entity).copied() {
+            event_queue.entity_orphaned(NetworkEntityOrphanedEvent {
+                entity,
+                network_entity,
+                owner,
+                policy,
+            });
+            if policy == NetworkOwnerLeavePolicy::Despawn {
+                world.despawn(entity);
+                NetworkEntities::remove(world, entity);
             }
         }
     }
@@ -454,16 +756,25 @@ pub fn entity_owner_send_events(network: &mut Network, world: &mut World) {
     let Network { state, .. } = network;
     match state {
         NetworkState::Connected { server, client } => {
+            let entity_map = NetworkEntities::current(world);
             let mut query = world.query::<(&NetworkEntity, &mut NetworkEntityOwner)>();
             for (network_entity, mut network_entity_owner) in query.iter_mut(world) {
                 while let Some(event) = network_entity_owner.events.pop_back() {
+                    let event = NetworkEntityMap::scope(&entity_map, event);
                     if let Some(server) = server {
                         let NetworkServer {
                             players,
                             relevancy,
                             local_player,
+                            update_rates,
                             ..
                         } = server;
+                        let message = NetworkMessage::EntityEvent {
+                            entity: *network_entity,
+                            from: None,
+                            data: event,
+                        }
+                        .serialize_shared();
                         for player in players.iter_mut() {
                             let is_local_player = if let Some(local_player) = local_player {
                                 player.handle == *local_player
@@ -473,13 +784,11 @@ pub fn entity_owner_send_events(network: &mut Network, world: &mut World) {
                             if !is_local_player
                                 && relevancy.relevant(player.handle, *network_entity)
                             {
-                                player.socket.send(
-                                    NetworkMessage::EntityEvent {
-                                        entity: *network_entity,
-                                        from: None,
-                                        data: event.clone(),
-                                    }
-                                    .serialize(),
+                                player.bandwidth.send(
+                                    &mut player.socket,
+                                    *network_entity,
+                                    message.clone(),
+                                    update_rates.contains_key(network_entity),
                                 );
                             }
                         }
@@ -513,6 +822,39 @@ pub fn server_accept_sockets(network: &mut Network) {
     }
 }
 
+// clients can't change server driven states, local changes are undone and sent as requests,
+// they're only seen once bevy applied them so the client runs the transition for a frame, and a
+// change queued in the same frame the server's state arrives is replaced without a request
+fn client_check_states(network: &mut Network, world: &mut World) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let client = if let NetworkState::Connected {
+        server: None,
+        client: Some(client),
+    } = state
+    {
+        client
+    } else {
+        return;
+    };
+    for (type_name, entry) in registry.entries() {
+        if let Some(registry_state) = &entry.state {
+            if (registry_state.retry)(world) {
+                continue;
+            }
+            if let Some(authoritative) = client.resources.get(type_name) {
+                if let Some(current) = (registry_state.current)(world) {
+                    if current.data != authoritative.data {
+                        (registry_state.overwrite)(world, authoritative.clone());
+                        client.send_state_request(current);
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub fn client_receive_messages(network: &mut Network) {
     let Network {
         state, event_queue, ..
@@ -538,36 +880,154 @@ pub fn client_receive_messages(network: &mut Network) {
             }
             NetworkMessage::PlayerLeave { player } => {
                 client.players.retain(|p| p.handle != player);
+                if let Some(rollback) = &mut client.rollback {
+                    rollback.remove_player(player);
+                }
                 event_queue.player_leave(NetworkPlayerLeaveEvent { player });
             }
             NetworkMessage::Event { data } => {
                 event_queue.network(data);
             }
-            NetworkMessage::EntitySpawn { entity } => {
-                // TODO: ensure that NetworkEntity doesn't already exist in hash map?
-                client.entities.insert(
-                    entity,
-                    NetworkClientEntity {
-                        initialized: false,
-                        exists: true,
-                        local_entity: None,
-                        owner: false,
-                    },
-                );
+            NetworkMessage::EntitySpawn {
+                entity,
+                owner,
+                prefab,
+                parent,
+            } => {
+                if let Some(existing) = client.entities.get_mut(&entity) {
+                    // spawned by this client, or respawned before the despawn went through
+                    existing.exists = true;
+                    existing.owner = owner;
+                    client.owner_changes.insert(entity);
+                    existing.parent = parent;
+                    existing.parent_changed = true;
+                } else {
+                    client.entities.insert(
+                        entity,
+                        NetworkClientEntity {
+                            initialized: false,
+                            exists: true,
+                            local_entity: None,
+                            owner,
+                            prefab,
+                            despawn_reason: NetworkEntityDespawnReason::Destroyed,
+                            pending: false,
+                            remapped: false,
+                            parent,
+                            parent_changed: parent.is_some(),
+                        },
+                    );
+                }
             }
-            NetworkMessage::EntityDespawn { entity } => {
+            NetworkMessage::EntityDespawn { entity, reason } => {
                 if let Some(entity) = client.entities.get_mut(&entity) {
                     entity.exists = false;
+                    entity.despawn_reason = reason;
                 }
             }
-            NetworkMessage::EntityOwner { entity, owner } => {
+            NetworkMessage::EntityOwner {
+                entity: handle,
+                owner,
+            } => {
+                if let Some(entity) = client.entities.get_mut(&handle) {
+                    if entity.owner != owner {
+                        event_queue.entity_ownership_changed(handle, entity.owner, owner);
+                        entity.owner = owner;
+                        client.owner_changes.insert(handle);
+                    }
+                }
+            }
+            NetworkMessage::EntityParent { entity, parent } => {
                 if let Some(entity) = client.entities.get_mut(&entity) {
-                    entity.owner = owner;
+                    entity.parent = parent;
+                    entity.parent_changed = true;
                 }
             }
             NetworkMessage::EntityEvent { entity, from, data } => {
                 event_queue.network_entity(entity, from, data);
             }
+            NetworkMessage::EntitySpawnResponse {
+                requested,
+                confirmed,
+            } => {
+                if let Some(mut entity) = client.entities.remove(&requested) {
+                    entity.pending = false;
+                    if let Some(local_entity) = entity.local_entity {
+                        event_queue.spawn_response(NetworkSpawnResponseEvent {
+                            entity: local_entity,
+                            requested,
+                            confirmed,
+                        });
+                    }
+                    if let Some(confirmed) = confirmed {
+                        entity.remapped = confirmed != requested;
+                        client.entities.insert(confirmed, entity);
+                        client.owner_changes.insert(confirmed);
+                    } else {
+                        entity.exists = false;
+                        entity.despawn_reason = NetworkEntityDespawnReason::Rejected;
+                        client.entities.insert(requested, entity);
+                    }
+                }
+            }
+            NetworkMessage::EntityOwnershipResponse {
+                entity,
+                release,
+                approved,
+            } => {
+                event_queue.ownership_response(entity, release, approved);
+            }
+            NetworkMessage::RollbackRemoteInput { player, tick, data } => {
+                // inputs of any other type are ignored by the session
+                if let Some(rollback) = &mut client.rollback {
+                    rollback.receive(player, tick, data);
+                }
+            }
+            NetworkMessage::RollbackStart {
+                type_name,
+                player,
+                tick,
+            } => {
+                let me = client.me;
+                let rollback = client
+                    .rollback
+                    .get_or_insert_with(|| NetworkRollbackSession::new(type_name.clone()));
+                if *rollback.input_type() == type_name {
+                    rollback.start(player, tick, me);
+                }
+            }
+            NetworkMessage::Resource { data } => {
+                client
+                    .resources
+                    .insert(data.type_name.clone(), data.clone());
+                event_queue.resource(data);
+            }
+            NetworkMessage::ResourceRemove { type_name } => {
+                client.resources.remove(&type_name);
+                event_queue.resource_removed(type_name);
+            }
+            NetworkMessage::LockstepStart { type_name, tick } => {
+                client.lockstep.entry(type_name).or_default().start(tick);
+            }
+            NetworkMessage::LockstepTick {
+                tick,
+                type_name,
+                inputs,
+            } => {
+                event_queue.lockstep_tick(type_name, tick, inputs);
+            }
+            NetworkMessage::LockstepDesync { tick, checksums } => {
+                event_queue.desync(NetworkDesyncEvent { tick, checksums });
+            }
+            NetworkMessage::ClockResponse {
+                client_time,
+                server_time,
+                server_tick,
+            } => {
+                client
+                    .clock
+                    .receive_response(client_time, server_time, server_tick);
+            }
             _ => {
                 // TODO: disconnect for bad data?
             }
@@ -592,6 +1052,12 @@ pub fn server_receive_messages_from_joiners(network: &mut Network) {
                             handle: player,
                             socket: joiner.socket.take().unwrap(),
                             data,
+                            rtt: 0.,
+                            interpolation_delay: 0.,
+                            inputs: NetworkInputBuffers::new(),
+                            resources: HashMap::new(),
+                            resources_sent: false,
+                            bandwidth: NetworkBandwidth::default(),
                         });
                         break;
                     }
@@ -648,9 +1114,80 @@ pub fn server_initialize_players(network: &mut Network) {
     }
 }
 
+// network resources are sent to players when they join, and again whenever they change,
+// unchanged resources aren't serialized
+fn server_send_resources(network: &mut Network, world: &mut World) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let server = get_server_from_state!(state);
+    let NetworkServer {
+        players,
+        local_player,
+        ..
+    } = server;
+    let entity_map = NetworkEntities::current(world);
+    for (type_name, entry) in registry.entries() {
+        let resource = if let Some(resource) = &entry.resource {
+            resource
+        } else {
+            continue;
+        };
+        let changed = (resource.changed)(world);
+        if !changed && players.iter().all(|player| player.resources_sent) {
+            continue;
+        }
+        let data = NetworkEntityMap::scope(&entity_map, || (resource.serialize)(world));
+        let mut message: Option<Arc<str>> = None;
+        for player in players.iter_mut() {
+            if !player.initialized || Some(player.handle) == *local_player {
+                continue;
+            }
+            if !changed && player.resources_sent {
+                continue;
+            }
+            let allowed = resource
+                .filter
+                .as_ref()
+                .map_or(true, |filter| filter(world, player.handle));
+            match data.as_ref().filter(|_| allowed) {
+                Some(data) => {
+                    let changed = player
+                        .resources
+                        .get(type_name)
+                        .map_or(true, |sent| sent.data != data.data);
+                    if changed {
+                        let message = message.get_or_insert_with(|| {
+                            NetworkMessage::Resource { data: data.clone() }.serialize_shared()
+                        });
+                        player.socket.send_shared(message.clone());
+                        player.resources.insert(type_name.clone(), data.clone());
+                    }
+                }
+                None => {
+                    if player.resources.remove(type_name).is_some() {
+                        player.socket.send(
+                            NetworkMessage::ResourceRemove {
+                                type_name: type_name.clone(),
+                            }
+                            .serialize(),
+                        );
+                    }
+                }
+            }
+        }
+    }
+    for player in players.iter_mut() {
+        player.resources_sent |= player.initialized;
+    }
+}
+
 pub fn server_receive_messages_from_players(network: &mut Network) {
     let Network {
-        state, event_queue, ..
+        state,
+        event_queue,
+        registry,
+        ..
     } = network;
     let server = get_server_from_state!(state);
     let NetworkServer {
@@ -658,12 +1195,21 @@ pub fn server_receive_messages_from_players(network: &mut Network) {
         local_player,
         relevancy,
         entities,
+        started,
+        tick,
+        lockstep,
+        lockstep_checksums,
+        rollback,
+        ownership_requests,
+        spawn_requests,
+        update_rates,
+        owner_changes,
         ..
     } = server;
     let players_unsafe = unsafe { &mut *(players as *mut Vec<NetworkServerPlayer>) };
     for player in players.iter_mut() {
         player.socket.update();
-        if let Some(message) = player.socket.receive() {
+        while let Some(message) = player.socket.receive() {
             let message = NetworkMessage::deserialize(&message);
             match message {
                 NetworkMessage::Event { data } => {
@@ -699,22 +1245,173 @@ pub fn server_receive_messages_from_players(network: &mut Network) {
                             }
                         }
                     } else {
+                        let message = NetworkMessage::EntityEvent {
+                            entity,
+                            from: None,
+                            data,
+                        }
+                        .serialize_shared();
                         for other_player in players_unsafe.iter_mut() {
                             if player.handle != other_player.handle
                                 && relevancy.relevant(other_player.handle, entity)
                             {
-                                other_player.socket.send(
-                                    NetworkMessage::EntityEvent {
-                                        entity,
-                                        from: None,
-                                        data: data.clone(),
-                                    }
-                                    .serialize(),
+                                other_player.bandwidth.send(
+                                    &mut other_player.socket,
+                                    entity,
+                                    message.clone(),
+                                    update_rates.contains_key(&entity),
                                 );
                             }
                         }
                     }
                 }
+                NetworkMessage::EntitySpawnRequest { entity, prefab } => {
+                    if entities.contains_key(&entity)
+                        || spawn_requests.contains_key(&(player.handle, entity))
+                    {
+                        player.socket.send(
+                            NetworkMessage::EntitySpawnResponse {
+                                requested: entity,
+                                confirmed: None,
+                            }
+                            .serialize(),
+                        );
+                    } else {
+                        spawn_requests.insert((player.handle, entity), *tick);
+                        event_queue.spawn_request(NetworkSpawnRequestEvent {
+                            player: player.handle,
+                            network_entity: entity,
+                            prefab,
+                        });
+                    }
+                }
+                NetworkMessage::StateRequest { data } => {
+                    event_queue.state_request(player.handle, data);
+                }
+                NetworkMessage::EntityOwnershipRequest { entity, release } => {
+                    // players can't ask for entities they don't know about
+                    let relevant = *local_player == Some(player.handle)
+                        || relevancy.relevant(player.handle, entity);
+                    let server_entity = if relevant {
+                        entities.get_mut(&entity)
+                    } else {
+                        None
+                    };
+                    let approved = if let Some(server_entity) = server_entity {
+                        let is_owner = server_entity.owner == Some(player.handle);
+                        if server_entity.reserved.is_some() {
+                            Some(false)
+                        } else if release {
+                            // owners can always give up an entity, it goes back to the server
+                            if is_owner {
+                                server_entity.set_owner(None);
+                                owner_changes.insert(entity);
+                            }
+                            Some(is_owner)
+                        } else if is_owner {
+                            Some(true)
+                        } else {
+                            match server_entity.ownership_policy {
+                                NetworkOwnershipPolicy::Manual => {
+                                    if !ownership_requests.contains(&(player.handle, entity)) {
+                                        ownership_requests.push((player.handle, entity));
+                                        event_queue.ownership_request(player.handle, entity);
+                                    }
+                                    None
+                                }
+                                NetworkOwnershipPolicy::Approve => {
+                                    server_entity.set_owner(Some(player.handle));
+                                    owner_changes.insert(entity);
+                                    Some(true)
+                                }
+                                NetworkOwnershipPolicy::ApproveIfUnowned => {
+                                    if server_entity.owner.is_none() {
+                                        server_entity.set_owner(Some(player.handle));
+                                        owner_changes.insert(entity);
+                                    }
+                                    Some(server_entity.owner == Some(player.handle))
+                                }
+                                NetworkOwnershipPolicy::Deny => Some(false),
+                            }
+                        }
+                    } else {
+                        Some(false)
+                    };
+                    if let Some(approved) = approved {
+                        player.socket.send(
+                            NetworkMessage::EntityOwnershipResponse {
+                                entity,
+                                release,
+                                approved,
+                            }
+                            .serialize(),
+                        );
+                    }
+                }
+                NetworkMessage::ClockRequest { client_time, rtt } => {
+                    player.rtt = rtt;
+                    player.socket.send(
+                        NetworkMessage::ClockResponse {
+                            client_time,
+                            server_time: started.elapsed().as_secs_f64(),
+                            server_tick: *tick,
+                        }
+                        .serialize(),
+                    );
+                }
+                NetworkMessage::InterpolationDelay { seconds } => {
+                    player.interpolation_delay = seconds;
+                }
+                NetworkMessage::RollbackInput { tick, data } => {
+                    rollback.receive(tick);
+                    let message = NetworkMessage::RollbackRemoteInput {
+                        player: player.handle,
+                        tick,
+                        data,
+                    }
+                    .serialize_shared();
+                    for other_player in players_unsafe.iter_mut() {
+                        if player.handle != other_player.handle {
+                            other_player.socket.send_shared(message.clone());
+                        }
+                    }
+                }
+                NetworkMessage::LockstepInput { tick, data } => {
+                    let registered = registry
+                        .get_entry_from_serialized(&data)
+                        .and_then(|entry| entry.lockstep.as_ref())
+                        .is_some();
+                    if registered {
+                        lockstep.entry(data.type_name.clone()).or_default().receive(
+                            player.handle,
+                            tick,
+                            data,
+                        );
+                    }
+                }
+                NetworkMessage::LockstepChecksum { tick, checksum } => {
+                    lockstep_checksums.receive(
+                        player.handle,
+                        tick,
+                        checksum,
+                        current_lockstep_tick(lockstep),
+                    );
+                }
+                NetworkMessage::Input { inputs } => {
+                    for (input_tick, input) in inputs {
+                        let registered = registry
+                            .get_entry_from_serialized(&input)
+                            .and_then(|entry| entry.input.as_ref())
+                            .is_some();
+                        if registered {
+                            player
+                                .inputs
+                                .entry(input.type_name.clone())
+                                .or_default()
+                                .receive(input_tick, input, *tick);
+                        }
+                    }
+                }
                 _ => {
                     // TODO: disconnect for bad data?
                 }
@@ -723,6 +1420,143 @@ pub fn server_receive_messages_from_players(network: &mut Network) {
     }
 }
 
+fn server_consume_inputs(network: &mut Network, world: &mut World) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let server = get_server_from_state!(state);
+    for (type_name, entry) in registry.entries() {
+        if let Some(input) = &entry.input {
+            let mut inputs = vec![];
+            for player in server.players.iter_mut() {
+                if let Some(buffer) = player.inputs.get_mut(type_name) {
+                    if let Some((data, repeated)) = buffer.consume(server.tick) {
+                        inputs.push((player.handle, data, repeated));
+                    }
+                }
+            }
+            (input.send_to_world)(world, server.tick, inputs);
+        }
+    }
+}
+
+fn server_expire_spawn_requests(network: &mut Network) {
+    let Network { state, .. } = network;
+    let server = get_server_from_state!(state);
+    server.expire_spawn_requests();
+}
+
+fn server_advance_lockstep(network: &mut Network) {
+    let Network {
+        state,
+        event_queue,
+        registry,
+        ..
+    } = network;
+    let server = get_server_from_state!(state);
+    let NetworkServer {
+        players,
+        local_player,
+        lockstep,
+        lockstep_checksums,
+        ..
+    } = server;
+    for (type_name, entry) in registry.entries() {
+        if entry.lockstep.is_some() {
+            let lockstep = lockstep.entry(type_name.clone()).or_default();
+            for player in players.iter_mut() {
+                if let Some(tick) = lockstep.add_player(player.handle) {
+                    player.socket.send(
+                        NetworkMessage::LockstepStart {
+                            type_name: type_name.clone(),
+                            tick,
+                        }
+                        .serialize(),
+                    );
+                }
+            }
+        }
+    }
+    for (type_name, lockstep) in lockstep.iter_mut() {
+        while let Some((tick, inputs)) = lockstep.advance() {
+            let message = NetworkMessage::LockstepTick {
+                tick,
+                type_name: type_name.clone(),
+                inputs,
+            }
+            .serialize_shared();
+            for player in players.iter_mut() {
+                player.socket.send_shared(message.clone());
+            }
+        }
+    }
+    let desyncs = lockstep_checksums.compare(
+        |tick| {
+            let mut participants = vec![];
+            for lockstep in lockstep.values() {
+                for player in lockstep.participants(tick) {
+                    if !participants.contains(&player) {
+                        participants.push(player);
+                    }
+                }
+            }
+            participants
+        },
+        current_lockstep_tick(lockstep),
+    );
+    for (tick, checksums) in desyncs {
+        if local_player.is_none() {
+            event_queue.desync(NetworkDesyncEvent {
+                tick,
+                checksums: checksums.clone(),
+            });
+        }
+        let message = NetworkMessage::LockstepDesync { tick, checksums }.serialize_shared();
+        for player in players.iter_mut() {
+            player.socket.send_shared(message.clone());
+        }
+    }
+}
+
+// tells every player when new players take part in the rollback session
+fn server_start_rollback(network: &mut Network) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let server = get_server_from_state!(state);
+    let type_name = if let Some(type_name) = registry.rollback_input_type() {
+        type_name
+    } else {
+        return;
+    };
+    let NetworkServer {
+        players, rollback, ..
+    } = server;
+    let mut started = vec![];
+    for player in players.iter() {
+        if let Some(tick) = rollback.add_player(player.handle) {
+            started.push((player.handle, tick));
+        }
+    }
+    for player in players.iter_mut() {
+        let is_new = started.iter().any(|(handle, _)| *handle == player.handle);
+        for (handle, tick) in rollback.participants() {
+            // new players hear about everyone, the others only about the new players
+            let is_start_new = started.iter().any(|(started, _)| started == handle);
+            if is_new || is_start_new {
+                player.socket.send(
+                    NetworkMessage::RollbackStart {
+                        type_name: type_name.clone(),
+                        player: *handle,
+                        tick: *tick,
+                    }
+                    .serialize(),
+                );
+            }
+        }
+    }
+}
+
 pub fn client_check_disconnect(network: &mut Network) {
     let Network {
         state, event_queue, ..
@@ -751,18 +1585,31 @@ pub fn server_check_disconnects(network: &mut Network) {
         .players
         .retain(|p| !disconnected_players.contains(&p.handle));
     for disconnected_player in disconnected_players.iter() {
+        for lockstep in server.lockstep.values_mut() {
+            lockstep.remove_player(*disconnected_player);
+        }
+        server
+            .lockstep_checksums
+            .remove_player(*disconnected_player);
+        server.rollback.remove_player(*disconnected_player);
+        server
+            .ownership_requests
+            .retain(|(player, _)| player != disconnected_player);
+        server
+            .spawn_requests
+            .retain(|(player, _), _| player != disconnected_player);
+        server.relevancy.remove_player(*disconnected_player);
         if server.local_player.is_none() {
             event_queue.player_leave(NetworkPlayerLeaveEvent {
                 player: *disconnected_player,
             });
         }
+        let message = NetworkMessage::PlayerLeave {
+            player: *disconnected_player,
+        }
+        .serialize_shared();
         for player in server.players.iter_mut() {
-            player.socket.send(
-                NetworkMessage::PlayerLeave {
-                    player: *disconnected_player,
-                }
-                .serialize(),
-            );
+            player.socket.send_shared(message.clone());
         }
     }
 }
@@ -776,21 +1623,130 @@ fn send_events(network: &mut Network, world: &mut World) {
     event_queue.send_to_world(world, registry);
 }
 
+fn update_clock(network: &mut Network, world: &mut World) {
+    let mut clock = world.get_resource_mut::<NetworkClock>().unwrap();
+    match &network.state {
+        NetworkState::Connected { server, client } => {
+            if let Some(server) = server {
+                let time = server.time();
+                clock.synchronized = true;
+                clock.server_time = time;
+                clock.server_tick = server.tick;
+                clock.server_tick_rate = if time > 0. {
+                    server.tick as f64 / time
+                } else {
+                    0.
+                };
+                clock.rtt = 0.;
+            } else if let Some(client) = client {
+                client.clock.update(&mut clock);
+            }
+        }
+        _ => {
+            *clock = NetworkClock::default();
+        }
+    }
+}
+
 fn client_spawn_despawn_entities(network: &mut Network, world: &mut World) {
-    let Network { state, .. } = network;
+    let Network {
+        state,
+        registry,
+        event_queue,
+        ..
+    } = network;
     let client = get_client_from_state!(state);
-    for (handle, entity) in client.entities.iter_mut() {
+    let order = parents_first(
+        &client
+            .entities
+            .iter()
+            .map(|(handle, entity)| (*handle, entity.parent))
+            .collect(),
+    );
+    for handle in order.iter() {
+        let entity = client.entities.get_mut(handle).unwrap();
+        if entity.remapped {
+            if let Some(local_entity) = entity.local_entity {
+                if world.get_entity(local_entity).is_some() {
+                    world.entity_mut(local_entity).insert(*handle);
+                    NetworkEntities::insert(world, local_entity, *handle);
+                }
+            }
+            entity.remapped = false;
+        }
         if !entity.initialized {
-            entity.local_entity = Some(world.spawn().insert(*handle).id());
+            let local_entity = {
+                let entity_map = NetworkEntities::current(world);
+                let mut local_entity = world.spawn();
+                local_entity.insert(*handle);
+                if let Some(prefab) = &entity.prefab {
+                    if let Some(entry) = registry.get_entry_from_type_name(&prefab.type_name) {
+                        if let Some(registry_prefab) = &entry.prefab {
+                            NetworkEntityMap::scope(&entity_map, || {
+                                (registry_prefab.spawn)(&mut local_entity, prefab)
+                            });
+                        }
+                    }
+                }
+                local_entity.id()
+            };
+            NetworkEntities::insert(world, local_entity, *handle);
+            entity.local_entity = Some(local_entity);
             entity.initialized = true;
+            event_queue.entity_spawn(NetworkEntitySpawnEvent {
+                entity: local_entity,
+                network_entity: *handle,
+            });
         }
         if !entity.exists {
             if let Some(local_entity) = entity.local_entity {
-                world.entity_mut(local_entity).despawn();
+                event_queue.entity_despawn(NetworkEntityDespawnEvent {
+                    entity: local_entity,
+                    network_entity: *handle,
+                    reason: entity.despawn_reason,
+                });
+                if let Some(mut local_entity) = world.get_entity_mut(local_entity) {
+                    if local_entity.contains::<NetworkDelayDespawn>() {
+                        local_entity.remove::<NetworkEntity>();
+                        local_entity.insert(NetworkDespawned {
+                            network_entity: *handle,
+                            reason: entity.despawn_reason,
+                        });
+                    } else {
+                        local_entity.despawn();
+                    }
+                }
+                NetworkEntities::remove(world, local_entity);
             }
         }
     }
     client.entities.retain(|_, entity| entity.exists);
+    let reparented: Vec<(NetworkEntity, Option<NetworkEntity>)> = client
+        .entities
+        .iter()
+        .filter(|(_, entity)| entity.parent_changed)
+        .map(|(handle, entity)| (*handle, entity.parent))
+        .collect();
+    for (handle, parent) in reparented {
+        let local_parent = if let Some(parent) = parent {
+            let local_parent = client
+                .entities
+                .get(&parent)
+                .and_then(|parent| parent.local_entity);
+            if local_parent.is_none() {
+                // the parent isn't spawned here yet, try again next update
+                continue;
+            }
+            local_parent
+        } else {
+            None
+        };
+        let entity = client.entities.get_mut(&handle).unwrap();
+        if let Some(local_entity) = entity.local_entity {
+            set_local_parent(world, local_entity, local_parent);
+        }
+        entity.parent_changed = false;
+    }
 }
 
 fn update_entities(network: &mut Network, world: &mut World) {
@@ -801,34 +1757,145 @@ fn update_entities(network: &mut Network, world: &mut World) {
             world.entity_mut(entity).despawn();
         }
     } else {
-        let unsafe_world = unsafe { &mut *(world as *mut World) };
-        let mut query = world.query::<(Entity, &NetworkEntity, Option<&NetworkEntityOwner>)>();
-        for (entity, network_entity, network_entity_owner) in query.iter(world) {
-            let is_owner = match &mut network.state {
-                NetworkState::Connected { server, client } => {
-                    if let Some(server) = server {
-                        server.is_entity_owner(*network_entity)
-                    } else if let Some(client) = client {
-                        client.is_entity_owner(*network_entity)
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
+        let (mut server, mut client) = match &mut network.state {
+            NetworkState::Connected { server, client } => (server.as_mut(), client.as_mut()),
+            _ => return,
+        };
+        // the server decides when there is one, the client's changes are still taken so they
+        // don't pile up
+        let client_changes = client
+            .as_mut()
+            .and_then(|client| client.take_owner_changes());
+        let owner_changes = if let Some(server) = server.as_mut() {
+            server.take_owner_changes()
+        } else {
+            client_changes
+        };
+        // only entities that just became network entities or whose owner changed are checked
+        let entities: Vec<(Entity, NetworkEntity)> = if let Some(owner_changes) = owner_changes {
+            let mut query =
+                world.query_filtered::<(Entity, &NetworkEntity), Changed<NetworkEntity>>();
+            let mut entities: Vec<(Entity, NetworkEntity)> = query
+                .iter(world)
+                .map(|(entity, network_entity)| (entity, *network_entity))
+                .collect();
+            let entity_map = NetworkEntities::current(world);
+            entities.extend(owner_changes.into_iter().filter_map(|network_entity| {
+                entity_map
+                    .local(network_entity)
+                    .map(|entity| (entity, network_entity))
+            }));
+            entities
+        } else {
+            let mut query = world.query::<(Entity, &NetworkEntity)>();
+            query
+                .iter(world)
+                .map(|(entity, network_entity)| (entity, *network_entity))
+                .collect()
+        };
+        for (entity, network_entity) in entities {
+            let is_owner = if let Some(server) = server.as_mut() {
+                server.is_entity_owner(network_entity)
+            } else if let Some(client) = client.as_mut() {
+                client.is_entity_owner(network_entity)
+            } else {
+                false
             };
-            if is_owner && network_entity_owner.is_none() {
-                unsafe_world
+            let has_owner = if let Some(entity) = world.get_entity(entity) {
+                entity.contains::<NetworkEntityOwner>()
+            } else {
+                continue;
+            };
+            if is_owner && !has_owner {
+                world
                     .entity_mut(entity)
                     .insert(NetworkEntityOwner::default());
-            } else if !is_owner && network_entity_owner.is_some() {
-                unsafe_world
-                    .entity_mut(entity)
-                    .remove::<NetworkEntityOwner>();
+            } else if !is_owner && has_owner {
+                world.entity_mut(entity).remove::<NetworkEntityOwner>();
             }
         }
     }
 }
 
+// network entities spawned on a client are sent to the server to confirm
+fn client_request_spawns(network: &mut Network, world: &mut World) {
+    let Network {
+        state, registry, ..
+    } = network;
+    let client = if let NetworkState::Connected {
+        server: None,
+        client: Some(client),
+    } = state
+    {
+        client
+    } else {
+        return;
+    };
+    let mut query = world.query::<(Entity, &NetworkEntity)>();
+    for (entity, network_entity) in query.iter(world) {
+        if client.entities.contains_key(network_entity) {
+            continue;
+        }
+        let prefab = prefab_payload(registry, world, entity);
+        client.socket.send(
+            NetworkMessage::EntitySpawnRequest {
+                entity: *network_entity,
+                prefab: prefab.clone(),
+            }
+            .serialize(),
+        );
+        client.entities.insert(
+            *network_entity,
+            NetworkClientEntity {
+                initialized: true,
+                exists: true,
+                local_entity: Some(entity),
+                owner: Some(client.me),
+                prefab,
+                despawn_reason: NetworkEntityDespawnReason::Destroyed,
+                pending: true,
+                remapped: false,
+                parent: None,
+                parent_changed: false,
+            },
+        );
+        client.owner_changes.insert(*network_entity);
+    }
+}
+
+// picks up the network entities game code spawned and despawned since the last update
+fn update_entity_map(world: &mut World) {
+    NetworkEntities::update(world);
+}
+
+// runs after the spawns are sent, so the other side knows the entities the events refer to
+pub fn send_deferred_events(network: &mut Network, world: &mut World) {
+    if let NetworkState::Connected { server, client } = &mut network.state {
+        let entity_map = NetworkEntities::current(world);
+        if let Some(server) = server {
+            for (target, event) in std::mem::take(&mut server.deferred_events) {
+                let data = NetworkEntityMap::scope(&entity_map, event);
+                server.send_event(target, data);
+            }
+        }
+        if let Some(client) = client {
+            for (entity, event) in std::mem::take(&mut client.deferred_events) {
+                let data = NetworkEntityMap::scope(&entity_map, event);
+                client.send_event(entity, data);
+            }
+        }
+    }
+}
+
+fn despawn_released_entities(world: &mut World) {
+    let mut query =
+        world.query_filtered::<Entity, (With<NetworkDespawned>, Without<NetworkDelayDespawn>)>();
+    let entities: Vec<Entity> = query.iter(world).collect();
+    for entity in entities {
+        world.entity_mut(entity).despawn();
+    }
+}
+
 pub fn server_send_entity_events(network: &mut Network) {
     let Network { state, .. } = network;
     let server = get_server_from_state!(state);
@@ -836,13 +1903,50 @@ pub fn server_send_entity_events(network: &mut Network) {
         players,
         relevancy,
         entity_messages,
+        update_rates,
         ..
     } = server;
     while let Some((entity, message)) = entity_messages.pop_front() {
+        let message = message.serialize_shared();
         for player in players.iter_mut() {
             if relevancy.relevant(player.handle, entity) {
-                player.socket.send(message.serialize());
+                player.bandwidth.send(
+                    &mut player.socket,
+                    entity,
+                    message.clone(),
+                    update_rates.contains_key(&entity),
+                );
             }
         }
     }
 }
+
+pub fn server_send_entity_updates(network: &mut Network, world: &mut World) {
+    let Network { state, .. } = network;
+    let server = get_server_from_state!(state);
+    let NetworkServer {
+        players,
+        relevancy,
+        update_rates,
+        tick,
+        ..
+    } = server;
+    if !players.iter().any(|player| player.bandwidth.has_pending()) {
+        return;
+    }
+    let mut priority_query = world.query::<(&NetworkEntity, &NetworkPriority)>();
+    let priorities: HashMap<NetworkEntity, NetworkPriority> = priority_query
+        .iter(world)
+        .map(|(network_entity, priority)| (*network_entity, *priority))
+        .collect();
+    for player in players.iter_mut() {
+        let handle = player.handle;
+        player.bandwidth.flush(&mut player.socket, |entity| {
+            let distance = relevancy.viewpoint_distance(handle, entity);
+            let due = update_rates
+                .get(&entity)
+                .map_or(true, |update_rate| update_rate.due(entity, *tick, distance));
+            due.then(|| update_priority(priorities.get(&entity).copied(), distance))
+        });
+    }
+}
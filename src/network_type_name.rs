@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
 
-#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct NetworkTypeName(String);
 
 impl NetworkTypeName {
@@ -1,23 +1,74 @@
 use crate::{
+    clock::NetworkClock,
+    entity_map::NetworkEntities,
     events::{
-        NetworkConnectEvent, NetworkConnectingEvent, NetworkDisconnectEvent,
-        NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkConnectEvent, NetworkConnectingEvent, NetworkDesyncEvent, NetworkDisconnectEvent,
+        NetworkEntityDespawnEvent, NetworkEntityEventDroppedEvent, NetworkEntityOrphanedEvent,
+        NetworkEntityOwnershipChangedEvent, NetworkEntitySpawnEvent, NetworkOwnershipRequestEvent,
+        NetworkOwnershipResponseEvent, NetworkPlayerJoinEvent, NetworkPlayerLeaveEvent,
+        NetworkSpawnRequestEvent, NetworkSpawnResponseEvent, NetworkSyncTestMismatchEvent,
     },
-    network::{update_network, Network},
+    network::{track_removals, update_network, Network},
+    relevancy::NetworkDefaultRelevancy,
+    rollback::{update_rollback, NetworkRollbackStage},
 };
 use bevy::prelude::*;
 
 pub struct NetworkPlugin;
 
+// the rollback simulation runs after the network, so it sees the inputs that just arrived
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum NetworkSystem {
+    Update,
+    Rollback,
+}
+
+// runs between PostUpdate and Last, removals are only remembered until Last starts
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub enum NetworkStage {
+    Cleanup,
+}
+
 impl Plugin for NetworkPlugin {
     fn build(&self, app: &mut App) {
         // TODO: what stage should network run? first? last?
         app.init_resource::<Network>()
+            .init_resource::<NetworkClock>()
+            .init_resource::<NetworkRollbackStage>()
+            .init_resource::<NetworkDefaultRelevancy>()
+            .init_resource::<NetworkEntities>()
             .add_event::<NetworkConnectEvent>()
             .add_event::<NetworkConnectingEvent>()
             .add_event::<NetworkDisconnectEvent>()
             .add_event::<NetworkPlayerJoinEvent>()
             .add_event::<NetworkPlayerLeaveEvent>()
-            .add_system(update_network.exclusive_system());
+            .add_event::<NetworkDesyncEvent>()
+            .add_event::<NetworkSyncTestMismatchEvent>()
+            .add_event::<NetworkEntitySpawnEvent>()
+            .add_event::<NetworkEntityDespawnEvent>()
+            .add_event::<NetworkEntityOwnershipChangedEvent>()
+            .add_event::<NetworkEntityOrphanedEvent>()
+            .add_event::<NetworkEntityEventDroppedEvent>()
+            .add_event::<NetworkSpawnRequestEvent>()
+            .add_event::<NetworkSpawnResponseEvent>()
+            .add_event::<NetworkOwnershipRequestEvent>()
+            .add_event::<NetworkOwnershipResponseEvent>()
+            .add_system(
+                update_network
+                    .exclusive_system()
+                    .label(NetworkSystem::Update),
+            )
+            .add_system(
+                update_rollback
+                    .exclusive_system()
+                    .label(NetworkSystem::Rollback)
+                    .after(NetworkSystem::Update),
+            )
+            .add_stage_before(
+                CoreStage::Last,
+                NetworkStage::Cleanup,
+                SystemStage::single_threaded(),
+            )
+            .add_system_to_stage(NetworkStage::Cleanup, track_removals.exclusive_system());
     }
 }
@@ -0,0 +1,57 @@
+use crate::{
+    entity_map::{NetworkEntities, NetworkEntityMap},
+    registry::NetworkRegistry,
+    serialized_struct::NetworkSerializedStruct,
+};
+use bevy::{ecs::world::EntityMut, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub trait NetworkPrefabTraits: Component + Serialize + DeserializeOwned + Clone {}
+impl<T> NetworkPrefabTraits for T where T: Component + Serialize + DeserializeOwned + Clone {}
+
+type NetworkPrefabPayloadFn =
+    Box<dyn Fn(&World, Entity) -> Option<NetworkSerializedStruct> + Send + Sync>;
+type NetworkPrefabSpawnFn = Box<dyn Fn(&mut EntityMut, &NetworkSerializedStruct) + Send + Sync>;
+
+pub struct NetworkRegistryPrefab {
+    pub(crate) payload: NetworkPrefabPayloadFn,
+    pub(crate) spawn: NetworkPrefabSpawnFn,
+}
+
+impl NetworkRegistryPrefab {
+    pub(crate) fn new<T, F>(builder: F) -> Self
+    where
+        T: NetworkPrefabTraits,
+        F: Fn(&mut EntityMut, &T) + Send + Sync + 'static,
+    {
+        Self {
+            payload: Box::new(|world: &World, entity: Entity| {
+                world
+                    .get::<T>(entity)
+                    .map(|payload| NetworkSerializedStruct::from_struct(payload))
+            }),
+            spawn: Box::new(
+                move |entity: &mut EntityMut, payload: &NetworkSerializedStruct| {
+                    if let Some(payload) = payload.to_struct::<T>() {
+                        builder(entity, &payload);
+                        entity.insert(payload);
+                    }
+                },
+            ),
+        }
+    }
+}
+
+// the spawn payload of the first registered prefab component on the entity
+pub(crate) fn prefab_payload(
+    registry: &NetworkRegistry,
+    world: &World,
+    entity: Entity,
+) -> Option<NetworkSerializedStruct> {
+    let entity_map = NetworkEntities::current_readonly(world);
+    NetworkEntityMap::scope(&entity_map, || {
+        registry
+            .prefabs()
+            .find_map(|prefab| (prefab.payload)(world, entity))
+    })
+}
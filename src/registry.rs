@@ -1,18 +1,37 @@
 use crate::{
     events::{NetworkEntityEvent, NetworkEvent, NetworkEventTraits, NetworkServerEvent},
+    input::{NetworkInputTraits, NetworkRegistryInput},
+    lag_compensation::{NetworkLagCompensationTraits, NetworkRegistryLagCompensation},
+    lockstep::{NetworkLockstepTraits, NetworkRegistryLockstep},
     network_type_name::NetworkTypeName,
     player::NetworkPlayer,
     player_data::NetworkPlayerDataTraits,
+    prefab::{NetworkPrefabTraits, NetworkRegistryPrefab},
+    relevancy::{NetworkRelevancyRule, NetworkRelevancyRuleMode, NetworkRelevancyRules},
+    resource::{NetworkRegistryResource, NetworkResourceTraits},
+    rollback::{
+        NetworkRegistryRollbackInput, NetworkRegistryRollbackState, NetworkRollbackComponentTraits,
+        NetworkRollbackInputTraits, NetworkRollbackResourceTraits,
+    },
     serialized_struct::NetworkSerializedStruct,
+    state::{NetworkRegistryState, NetworkStateTraits},
 };
-use bevy::{app::Events, prelude::*};
-use std::collections::HashMap;
+use bevy::{app::Events, ecs::world::EntityMut, prelude::*};
+use std::{any::type_name, collections::HashMap};
 
 #[derive(Default)]
 pub struct NetworkRegistryEntry {
     pub(crate) event: Option<NetworkRegistryEvent>,
     pub(crate) entity_event: Option<NetworkRegistryEntityEvent>,
     pub(crate) player_data: Option<NetworkRegistryPlayerData>,
+    pub(crate) lag_compensation: Option<NetworkRegistryLagCompensation>,
+    pub(crate) input: Option<NetworkRegistryInput>,
+    pub(crate) lockstep: Option<NetworkRegistryLockstep>,
+    pub(crate) rollback_input: Option<NetworkRegistryRollbackInput>,
+    pub(crate) rollback_state: Option<NetworkRegistryRollbackState>,
+    pub(crate) prefab: Option<NetworkRegistryPrefab>,
+    pub(crate) resource: Option<NetworkRegistryResource>,
+    pub(crate) state: Option<NetworkRegistryState>,
 }
 
 pub struct NetworkRegistryEvent {
@@ -28,20 +47,19 @@ impl NetworkRegistryEvent {
     {
         Self {
             send_to_world: Box::new(|world: &mut World, s: NetworkSerializedStruct| {
-                let mut events = world.get_resource_mut::<Events<NetworkEvent<T>>>().unwrap();
-                events.send(NetworkEvent {
-                    data: s.to_struct::<T>().unwrap(),
-                });
+                if let Some(data) = s.to_struct::<T>() {
+                    let mut events = world.get_resource_mut::<Events<NetworkEvent<T>>>().unwrap();
+                    events.send(NetworkEvent { data });
+                }
             }),
             send_to_server_world: Box::new(
                 |world: &mut World, from: NetworkPlayer, s: NetworkSerializedStruct| {
-                    let mut events = world
-                        .get_resource_mut::<Events<NetworkServerEvent<T>>>()
-                        .unwrap();
-                    events.send(NetworkServerEvent {
-                        from,
-                        data: s.to_struct::<T>().unwrap(),
-                    });
+                    if let Some(data) = s.to_struct::<T>() {
+                        let mut events = world
+                            .get_resource_mut::<Events<NetworkServerEvent<T>>>()
+                            .unwrap();
+                        events.send(NetworkServerEvent { from, data });
+                    }
                 },
             ),
         }
@@ -65,14 +83,12 @@ impl NetworkRegistryEntityEvent {
                  entity: Entity,
                  from: Option<NetworkPlayer>,
                  s: NetworkSerializedStruct| {
-                    let mut events = world
-                        .get_resource_mut::<Events<NetworkEntityEvent<T>>>()
-                        .unwrap();
-                    events.send(NetworkEntityEvent {
-                        from,
-                        entity,
-                        data: s.to_struct::<T>().unwrap(),
-                    });
+                    if let Some(data) = s.to_struct::<T>() {
+                        let mut events = world
+                            .get_resource_mut::<Events<NetworkEntityEvent<T>>>()
+                            .unwrap();
+                        events.send(NetworkEntityEvent { from, entity, data });
+                    }
                 },
             ),
         }
@@ -93,6 +109,10 @@ impl NetworkRegistryPlayerData {
 #[derive(Default)]
 pub struct NetworkRegistry {
     entries: HashMap<NetworkTypeName, NetworkRegistryEntry>,
+    // prefab types in the order they were registered, so an entity's prefab doesn't depend on
+    // hash map order
+    prefabs: Vec<NetworkTypeName>,
+    pub(crate) relevancy_rules: NetworkRelevancyRules,
 }
 
 impl NetworkRegistry {
@@ -141,6 +161,71 @@ impl NetworkRegistry {
         entry.player_data.as_mut().unwrap()
     }
 
+    fn get_or_insert_lag_compensation<T>(
+        &mut self,
+        type_name: NetworkTypeName,
+    ) -> &mut NetworkRegistryLagCompensation
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        let entry = self.get_or_insert_entry(type_name);
+        if entry.lag_compensation.is_none() {
+            entry.lag_compensation = Some(NetworkRegistryLagCompensation::new::<T>());
+        }
+        entry.lag_compensation.as_mut().unwrap()
+    }
+
+    fn get_or_insert_input<T>(&mut self, type_name: NetworkTypeName) -> &mut NetworkRegistryInput
+    where
+        T: NetworkInputTraits,
+    {
+        let entry = self.get_or_insert_entry(type_name);
+        if entry.input.is_none() {
+            entry.input = Some(NetworkRegistryInput::new::<T>());
+        }
+        entry.input.as_mut().unwrap()
+    }
+
+    fn get_or_insert_lockstep<T>(
+        &mut self,
+        type_name: NetworkTypeName,
+    ) -> &mut NetworkRegistryLockstep
+    where
+        T: NetworkLockstepTraits,
+    {
+        let entry = self.get_or_insert_entry(type_name);
+        if entry.lockstep.is_none() {
+            entry.lockstep = Some(NetworkRegistryLockstep::new::<T>());
+        }
+        entry.lockstep.as_mut().unwrap()
+    }
+
+    fn get_or_insert_rollback_input<T>(
+        &mut self,
+        type_name: NetworkTypeName,
+    ) -> &mut NetworkRegistryRollbackInput
+    where
+        T: NetworkRollbackInputTraits,
+    {
+        let entry = self.get_or_insert_entry(type_name);
+        if entry.rollback_input.is_none() {
+            entry.rollback_input = Some(NetworkRegistryRollbackInput::new::<T>());
+        }
+        entry.rollback_input.as_mut().unwrap()
+    }
+
+    fn get_or_insert_rollback_state(
+        &mut self,
+        type_name: NetworkTypeName,
+        rollback_state: fn() -> NetworkRegistryRollbackState,
+    ) -> &mut NetworkRegistryRollbackState {
+        let entry = self.get_or_insert_entry(type_name);
+        if entry.rollback_state.is_none() {
+            entry.rollback_state = Some(rollback_state());
+        }
+        entry.rollback_state.as_mut().unwrap()
+    }
+
     pub fn add_network_event<T>(&mut self)
     where
         T: NetworkEventTraits,
@@ -162,10 +247,140 @@ impl NetworkRegistry {
         self.get_or_insert_player_data::<T>(NetworkTypeName::of::<T>());
     }
 
+    pub fn add_network_lag_compensation<T>(&mut self)
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        self.get_or_insert_lag_compensation::<T>(NetworkTypeName::of::<T>());
+    }
+
+    pub fn add_network_input<T>(&mut self)
+    where
+        T: NetworkInputTraits,
+    {
+        self.get_or_insert_input::<T>(NetworkTypeName::of::<T>());
+    }
+
+    pub fn add_network_lockstep<T>(&mut self)
+    where
+        T: NetworkLockstepTraits,
+    {
+        self.get_or_insert_lockstep::<T>(NetworkTypeName::of::<T>());
+    }
+
+    // a client runs a single rollback session, so only one input type can drive it
+    pub fn add_network_rollback<T>(&mut self)
+    where
+        T: NetworkRollbackInputTraits,
+    {
+        let input_type = NetworkTypeName::of::<T>();
+        if let Some(existing) = self.rollback_input_type() {
+            if existing != input_type {
+                panic!(
+                    "Cannot register \"{}\" for rollback, another input type already is.",
+                    type_name::<T>()
+                );
+            }
+        }
+        self.get_or_insert_rollback_input::<T>(input_type);
+    }
+
+    pub(crate) fn rollback_input_type(&self) -> Option<NetworkTypeName> {
+        self.entries
+            .iter()
+            .find(|(_, entry)| entry.rollback_input.is_some())
+            .map(|(type_name, _)| type_name.clone())
+    }
+
+    pub fn add_network_rollback_component<T>(&mut self)
+    where
+        T: NetworkRollbackComponentTraits,
+    {
+        self.get_or_insert_rollback_state(
+            NetworkTypeName::of::<T>(),
+            NetworkRegistryRollbackState::component::<T>,
+        );
+    }
+
+    pub fn add_network_rollback_resource<T>(&mut self)
+    where
+        T: NetworkRollbackResourceTraits,
+    {
+        self.get_or_insert_rollback_state(
+            NetworkTypeName::of::<T>(),
+            NetworkRegistryRollbackState::resource::<T>,
+        );
+    }
+
+    pub fn add_network_prefab<T, F>(&mut self, builder: F)
+    where
+        T: NetworkPrefabTraits,
+        F: Fn(&mut EntityMut, &T) + Send + Sync + 'static,
+    {
+        let type_name = NetworkTypeName::of::<T>();
+        if !self.prefabs.contains(&type_name) {
+            self.prefabs.push(type_name.clone());
+        }
+        self.get_or_insert_entry(type_name).prefab =
+            Some(NetworkRegistryPrefab::new::<T, F>(builder));
+    }
+
+    pub fn add_network_resource<T>(&mut self)
+    where
+        T: NetworkResourceTraits,
+    {
+        self.get_or_insert_entry(NetworkTypeName::of::<T>())
+            .resource = Some(NetworkRegistryResource::new::<T>());
+    }
+
+    pub fn add_network_resource_filtered<T, F>(&mut self, filter: F)
+    where
+        T: NetworkResourceTraits,
+        F: Fn(NetworkPlayer, &T) -> bool + Send + Sync + 'static,
+    {
+        self.get_or_insert_entry(NetworkTypeName::of::<T>())
+            .resource = Some(NetworkRegistryResource::filtered::<T, F>(filter));
+    }
+
+    pub fn add_network_state<S>(&mut self)
+    where
+        S: NetworkStateTraits,
+    {
+        let entry = self.get_or_insert_entry(NetworkTypeName::of::<S>());
+        entry.resource = Some(NetworkRegistryState::resource::<S>());
+        entry.state = Some(NetworkRegistryState::new::<S>());
+    }
+
+    pub fn add_network_relevancy_rule<R>(&mut self, mode: NetworkRelevancyRuleMode, rule: R)
+    where
+        R: NetworkRelevancyRule,
+    {
+        self.relevancy_rules.add(mode, Box::new(rule));
+    }
+
+    pub(crate) fn entries(
+        &self,
+    ) -> impl Iterator<Item = (&NetworkTypeName, &NetworkRegistryEntry)> {
+        self.entries.iter()
+    }
+
+    pub(crate) fn prefabs(&self) -> impl Iterator<Item = &NetworkRegistryPrefab> {
+        self.prefabs
+            .iter()
+            .filter_map(|type_name| self.entries[type_name].prefab.as_ref())
+    }
+
     pub fn get_entry<T>(&mut self) -> Option<&mut NetworkRegistryEntry> {
         self.entries.get_mut(&NetworkTypeName::of::<T>())
     }
 
+    pub fn get_entry_from_type_name(
+        &mut self,
+        type_name: &NetworkTypeName,
+    ) -> Option<&mut NetworkRegistryEntry> {
+        self.entries.get_mut(type_name)
+    }
+
     pub fn get_entry_from_serialized(
         &mut self,
         s: &NetworkSerializedStruct,
@@ -1,13 +1,141 @@
-use crate::{entity::NetworkEntity, player::NetworkPlayer, server::NetworkServerEntity};
-use std::collections::HashMap;
+use crate::{
+    entity::NetworkEntity, interest::NetworkSpatialInterest, player::NetworkPlayer,
+    server::NetworkServerEntity,
+};
+use bevy::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 struct NetworkRelevancyEntry {
     spawned: bool,
-    despawned: bool,
     relevant: Option<bool>,
-    manual_relevancy: bool,
+    // set through NetworkServer::set_entity_relevant, wins over NetworkVisibility and the default
+    manual_relevancy: Option<bool>,
+    in_range: bool,
 }
 
+// whether entities are relevant to players that nothing else decided for
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum NetworkDefaultRelevancy {
+    #[default]
+    All,
+    // only relevant to the owner until made relevant to other players
+    OwnerOnly,
+}
+
+// decides which players an entity is relevant to, read by the server when it spawns entities,
+// blocked players win over allowed players
+#[derive(Component, Debug, Clone, Default)]
+pub struct NetworkVisibility {
+    pub allowed: HashSet<NetworkPlayer>,
+    pub blocked: HashSet<NetworkPlayer>,
+}
+
+impl NetworkVisibility {
+    pub fn allow(mut self, player: NetworkPlayer) -> Self {
+        self.allowed.insert(player);
+        self
+    }
+
+    pub fn block(mut self, player: NetworkPlayer) -> Self {
+        self.blocked.insert(player);
+        self
+    }
+
+    pub(crate) fn visible(&self, player: NetworkPlayer) -> Option<bool> {
+        if self.blocked.contains(&player) {
+            Some(false)
+        } else if self.allowed.contains(&player) {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+// how a custom rule combines with the built in relevancy (manual flags, groups and spatial
+// interest), the entity is relevant when the built in relevancy and every And rule agree,
+// or when any Or rule does
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NetworkRelevancyRuleMode {
+    And,
+    Or,
+}
+
+// evaluated on the server whenever an entity or player is evaluated again: when it's first seen,
+// moves, or its owner, parent, groups or visibility change. rules that read anything else need
+// NetworkServer::refresh_entity_relevancy or refresh_player_relevancy when it changes
+pub trait NetworkRelevancyRule: Send + Sync + 'static {
+    fn relevant(&self, world: &World, player: NetworkPlayer, entity: Entity) -> bool;
+}
+
+impl<F> NetworkRelevancyRule for F
+where
+    F: Fn(&World, NetworkPlayer, Entity) -> bool + Send + Sync + 'static,
+{
+    fn relevant(&self, world: &World, player: NetworkPlayer, entity: Entity) -> bool {
+        self(world, player, entity)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct NetworkRelevancyRules {
+    rules: Vec<(NetworkRelevancyRuleMode, Box<dyn NetworkRelevancyRule>)>,
+}
+
+// the combined result of the custom rules for one player and entity
+#[derive(Copy, Clone)]
+pub(crate) struct NetworkRuleRelevancy {
+    all: bool,
+    any: bool,
+}
+
+impl Default for NetworkRuleRelevancy {
+    fn default() -> Self {
+        Self {
+            all: true,
+            any: false,
+        }
+    }
+}
+
+impl NetworkRelevancyRules {
+    pub(crate) fn add(
+        &mut self,
+        mode: NetworkRelevancyRuleMode,
+        rule: Box<dyn NetworkRelevancyRule>,
+    ) {
+        self.rules.push((mode, rule));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub(crate) fn evaluate(
+        &self,
+        world: &World,
+        player: NetworkPlayer,
+        entity: Entity,
+    ) -> NetworkRuleRelevancy {
+        let mut result = NetworkRuleRelevancy::default();
+        for (mode, rule) in self.rules.iter() {
+            match mode {
+                NetworkRelevancyRuleMode::And => {
+                    result.all = result.all && rule.relevant(world, player, entity);
+                }
+                NetworkRelevancyRuleMode::Or => {
+                    result.any = result.any || rule.relevant(world, player, entity);
+                }
+            }
+        }
+        result
+    }
+}
+
+// spawn and despawn are only returned on the update the relevancy changes
 pub(crate) enum NetworkRelevancyState {
     Spawn,
     Despawn,
@@ -15,10 +143,23 @@ pub(crate) enum NetworkRelevancyState {
     Irrelevant,
 }
 
+// entries are removed when players leave and entities despawn, and only the players and
+// entities that changed since the last update are evaluated again
 #[derive(Default)]
 pub(crate) struct NetworkRelevancy {
-    // TODO: cleanup this hashmap
     relevancy: HashMap<NetworkPlayer, HashMap<NetworkEntity, NetworkRelevancyEntry>>,
+    players: HashSet<NetworkPlayer>,
+    entities: HashSet<NetworkEntity>,
+    dirty_players: HashSet<NetworkPlayer>,
+    dirty_entities: HashSet<NetworkEntity>,
+    all_dirty: bool,
+    default_relevancy: NetworkDefaultRelevancy,
+    spatial_interest: Option<NetworkSpatialInterest>,
+    viewpoints: HashMap<NetworkPlayer, NetworkEntity>,
+    positions: HashMap<NetworkEntity, Vec3>,
+    // entities in groups are only relevant to players sharing one of their groups
+    player_groups: HashMap<NetworkPlayer, HashSet<String>>,
+    entity_groups: HashMap<NetworkEntity, HashSet<String>>,
 }
 
 impl NetworkRelevancy {
@@ -35,9 +176,9 @@ impl NetworkRelevancy {
             .entry(entity)
             .or_insert_with(|| NetworkRelevancyEntry {
                 spawned: false,
-                despawned: false,
                 relevant: None,
-                manual_relevancy: true,
+                manual_relevancy: None,
+                in_range: false,
             })
     }
 
@@ -46,31 +187,197 @@ impl NetworkRelevancy {
         player: NetworkPlayer,
         entity: &NetworkServerEntity,
         force_relevant: bool,
+        inherited: Option<bool>,
+        rules: NetworkRuleRelevancy,
+        visibility: Option<bool>,
     ) -> NetworkRelevancyState {
+        let in_group = self.shares_group(player, entity.handle);
+        let distance = self.viewpoint_distance(player, entity.handle);
+        let spatial_interest = self.spatial_interest;
+        let default_relevancy = self.default_relevancy == NetworkDefaultRelevancy::All;
         let entry = self.get_or_insert_entry(player, entity.handle);
-        entry.relevant = Some(entry.manual_relevancy || force_relevant);
-        if entry.manual_relevancy || force_relevant {
-            if entry.spawned {
-                NetworkRelevancyState::Relevant
+        // players without a viewpoint and entities without a position aren't filtered
+        let in_range =
+            if let (Some(spatial_interest), Some(distance)) = (spatial_interest, distance) {
+                entry.in_range = spatial_interest.in_range(distance, entry.in_range);
+                entry.in_range
             } else {
+                true
+            };
+        let manual_relevancy = entry
+            .manual_relevancy
+            .or(visibility)
+            .unwrap_or(default_relevancy);
+        let built_in = manual_relevancy && in_range && in_group;
+        // children following their parent also need to be relevant on their own
+        let relevant =
+            inherited.unwrap_or(true) && ((built_in && rules.all) || rules.any) || force_relevant;
+        entry.relevant = Some(relevant);
+        match (relevant, entry.spawned) {
+            (true, true) => NetworkRelevancyState::Relevant,
+            (true, false) => {
                 entry.spawned = true;
-                entry.despawned = false;
                 NetworkRelevancyState::Spawn
             }
-        } else {
-            if entry.despawned {
+            (false, true) => {
+                entry.spawned = false;
                 NetworkRelevancyState::Despawn
+            }
+            (false, false) => NetworkRelevancyState::Irrelevant,
+        }
+    }
+
+    // players seen for the first time are evaluated against every entity
+    pub(crate) fn track_player(&mut self, player: NetworkPlayer) {
+        if self.players.insert(player) {
+            self.dirty_players.insert(player);
+        }
+    }
+
+    // entities seen for the first time are evaluated for every player
+    pub(crate) fn track_entity(&mut self, entity: NetworkEntity) {
+        if self.entities.insert(entity) {
+            self.dirty_entities.insert(entity);
+        }
+    }
+
+    pub(crate) fn set_entity_dirty(&mut self, entity: NetworkEntity) {
+        self.dirty_entities.insert(entity);
+    }
+
+    pub(crate) fn set_player_dirty(&mut self, player: NetworkPlayer) {
+        self.dirty_players.insert(player);
+    }
+
+    // moved entities are evaluated again, and so is every entity for players whose viewpoint moved
+    pub(crate) fn set_moved(&mut self, moved: &HashSet<NetworkEntity>) {
+        for (player, viewpoint) in self.viewpoints.iter() {
+            if moved.contains(viewpoint) {
+                self.dirty_players.insert(*player);
+            }
+        }
+        self.dirty_entities.extend(moved.iter().copied());
+    }
+
+    pub(crate) fn is_entity_dirty(&self, entity: NetworkEntity) -> bool {
+        self.all_dirty || self.dirty_entities.contains(&entity)
+    }
+
+    pub(crate) fn is_player_dirty(&self, player: NetworkPlayer) -> bool {
+        self.dirty_players.contains(&player)
+    }
+
+    pub(crate) fn has_dirty_players(&self) -> bool {
+        !self.dirty_players.is_empty()
+    }
+
+    pub(crate) fn clear_dirty(&mut self) {
+        self.dirty_players.clear();
+        self.dirty_entities.clear();
+        self.all_dirty = false;
+    }
+
+    pub(crate) fn remove_player(&mut self, player: NetworkPlayer) {
+        self.relevancy.remove(&player);
+        self.players.remove(&player);
+        self.dirty_players.remove(&player);
+        self.viewpoints.remove(&player);
+        self.player_groups.remove(&player);
+    }
+
+    pub(crate) fn remove_entity(&mut self, entity: NetworkEntity) {
+        for entity_map in self.relevancy.values_mut() {
+            entity_map.remove(&entity);
+        }
+        self.entities.remove(&entity);
+        self.dirty_entities.remove(&entity);
+        self.positions.remove(&entity);
+        self.entity_groups.remove(&entity);
+    }
+
+    pub(crate) fn entry_count(&self) -> usize {
+        self.relevancy
+            .values()
+            .map(|entity_map| entity_map.len())
+            .sum()
+    }
+
+    fn shares_group(&self, player: NetworkPlayer, entity: NetworkEntity) -> bool {
+        if let Some(entity_groups) = self.entity_groups.get(&entity) {
+            if let Some(player_groups) = self.player_groups.get(&player) {
+                !entity_groups.is_disjoint(player_groups)
             } else {
-                entry.despawned = true;
-                entry.spawned = false;
-                NetworkRelevancyState::Irrelevant
+                false
             }
+        } else {
+            true
         }
     }
 
-    pub(crate) fn relevant(&mut self, player: NetworkPlayer, entity: NetworkEntity) -> bool {
-        // Note: The unwrap here is to ensure that relevancy is only checked AFTER update()
-        self.get_or_insert_entry(player, entity).relevant.unwrap()
+    pub(crate) fn set_player_group(&mut self, player: NetworkPlayer, group: &str, member: bool) {
+        set_group(&mut self.player_groups, player, group, member);
+        self.dirty_players.insert(player);
+    }
+
+    pub(crate) fn set_entity_group(&mut self, entity: NetworkEntity, group: &str, member: bool) {
+        set_group(&mut self.entity_groups, entity, group, member);
+        self.dirty_entities.insert(entity);
+    }
+
+    pub(crate) fn viewpoint_distance(
+        &self,
+        player: NetworkPlayer,
+        entity: NetworkEntity,
+    ) -> Option<f32> {
+        let viewpoint = self.viewpoints.get(&player)?;
+        let viewpoint_position = self.positions.get(viewpoint)?;
+        let position = self.positions.get(&entity)?;
+        Some(viewpoint_position.distance(*position))
+    }
+
+    pub(crate) fn set_default_relevancy(&mut self, default_relevancy: NetworkDefaultRelevancy) {
+        if self.default_relevancy != default_relevancy {
+            self.default_relevancy = default_relevancy;
+            self.all_dirty = true;
+        }
+    }
+
+    pub(crate) fn spatial_interest(&self) -> Option<NetworkSpatialInterest> {
+        self.spatial_interest
+    }
+
+    pub(crate) fn set_spatial_interest(
+        &mut self,
+        spatial_interest: Option<NetworkSpatialInterest>,
+    ) {
+        self.spatial_interest = spatial_interest;
+        self.all_dirty = true;
+    }
+
+    pub(crate) fn set_viewpoint(&mut self, player: NetworkPlayer, entity: Option<NetworkEntity>) {
+        if let Some(entity) = entity {
+            self.viewpoints.insert(player, entity);
+        } else {
+            self.viewpoints.remove(&player);
+        }
+        self.dirty_players.insert(player);
+    }
+
+    pub(crate) fn has_viewpoints(&self) -> bool {
+        !self.viewpoints.is_empty()
+    }
+
+    pub(crate) fn set_positions(&mut self, positions: HashMap<NetworkEntity, Vec3>) {
+        self.positions = positions;
+    }
+
+    // entities that weren't evaluated for the player yet aren't relevant to it
+    pub(crate) fn relevant(&self, player: NetworkPlayer, entity: NetworkEntity) -> bool {
+        self.relevancy
+            .get(&player)
+            .and_then(|entity_map| entity_map.get(&entity))
+            .and_then(|entry| entry.relevant)
+            .unwrap_or(false)
     }
 
     pub(crate) fn set_relevant(
@@ -79,7 +386,21 @@ impl NetworkRelevancy {
         entity: NetworkEntity,
         relevant: bool,
     ) {
-        self.get_or_insert_entry(player, entity).manual_relevancy = relevant;
-        self.get_or_insert_entry(player, entity).relevant = None;
+        self.get_or_insert_entry(player, entity).manual_relevancy = Some(relevant);
+        self.dirty_entities.insert(entity);
+    }
+}
+
+fn set_group<K>(groups: &mut HashMap<K, HashSet<String>>, key: K, group: &str, member: bool)
+where
+    K: Eq + Hash,
+{
+    if member {
+        groups.entry(key).or_default().insert(group.to_owned());
+    } else if let Some(key_groups) = groups.get_mut(&key) {
+        key_groups.remove(group);
+        if key_groups.is_empty() {
+            groups.remove(&key);
+        }
     }
 }
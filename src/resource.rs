@@ -0,0 +1,66 @@
+use crate::{player::NetworkPlayer, serialized_struct::NetworkSerializedStruct};
+use bevy::{ecs::system::Resource, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub trait NetworkResourceTraits: Resource + Serialize + DeserializeOwned {}
+impl<T> NetworkResourceTraits for T where T: Resource + Serialize + DeserializeOwned {}
+
+type NetworkResourceSerializeFn =
+    Box<dyn Fn(&World) -> Option<NetworkSerializedStruct> + Send + Sync>;
+type NetworkResourceChangedFn = Box<dyn Fn(&World) -> bool + Send + Sync>;
+type NetworkResourceFilterFn = Box<dyn Fn(&World, NetworkPlayer) -> bool + Send + Sync>;
+type NetworkResourceInsertFn = Box<dyn Fn(&mut World, NetworkSerializedStruct) + Send + Sync>;
+type NetworkResourceRemoveFn = Box<dyn Fn(&mut World) + Send + Sync>;
+
+pub struct NetworkRegistryResource {
+    pub(crate) serialize: NetworkResourceSerializeFn,
+    // whether the resource changed since the last update, missing resources count as changed
+    // so their removal reaches the players
+    pub(crate) changed: NetworkResourceChangedFn,
+    // decides which players get the resource, every player gets it when there's no filter
+    pub(crate) filter: Option<NetworkResourceFilterFn>,
+    pub(crate) insert: NetworkResourceInsertFn,
+    pub(crate) remove: NetworkResourceRemoveFn,
+}
+
+impl NetworkRegistryResource {
+    pub(crate) fn new<T>() -> Self
+    where
+        T: NetworkResourceTraits,
+    {
+        Self {
+            serialize: Box::new(|world: &World| {
+                world
+                    .get_resource::<T>()
+                    .map(|resource| NetworkSerializedStruct::from_struct(resource))
+            }),
+            changed: Box::new(|world: &World| {
+                world.is_resource_changed::<T>() || !world.contains_resource::<T>()
+            }),
+            filter: None,
+            insert: Box::new(|world: &mut World, data: NetworkSerializedStruct| {
+                if let Some(resource) = data.to_struct::<T>() {
+                    world.insert_resource(resource);
+                }
+            }),
+            remove: Box::new(|world: &mut World| {
+                world.remove_resource::<T>();
+            }),
+        }
+    }
+
+    pub(crate) fn filtered<T, F>(filter: F) -> Self
+    where
+        T: NetworkResourceTraits,
+        F: Fn(NetworkPlayer, &T) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            filter: Some(Box::new(move |world: &World, player: NetworkPlayer| {
+                world
+                    .get_resource::<T>()
+                    .map_or(false, |resource| filter(player, resource))
+            })),
+            ..Self::new::<T>()
+        }
+    }
+}
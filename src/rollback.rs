@@ -0,0 +1,553 @@
+use crate::{
+    events::{NetworkDesyncEvent, NetworkSyncTestMismatchEvent},
+    input::NetworkInputTraits,
+    network::Network,
+    network_type_name::NetworkTypeName,
+    player::NetworkPlayer,
+    registry::NetworkRegistry,
+    serialized_struct::NetworkSerializedStruct,
+    serializer::serialize,
+};
+use bevy::{app::Events, ecs::system::Resource, prelude::*};
+use serde::Serialize;
+use std::{
+    any::Any,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+// how many ticks can be rolled back, and how far ahead of remote players a peer may predict
+pub(crate) const MAX_ROLLBACK_TICKS: u64 = 16;
+
+pub trait NetworkRollbackComponentTraits: Component + Clone + Serialize {}
+impl<T> NetworkRollbackComponentTraits for T where T: Component + Clone + Serialize {}
+
+pub trait NetworkRollbackResourceTraits: Resource + Clone + Serialize {}
+impl<T> NetworkRollbackResourceTraits for T where T: Resource + Clone + Serialize {}
+
+pub trait NetworkRollbackInputTraits: NetworkInputTraits + Default {}
+impl<T> NetworkRollbackInputTraits for T where T: NetworkInputTraits + Default {}
+
+pub struct NetworkRollbackInput<T> {
+    pub data: T,
+    // true when the input hasn't arrived yet and was predicted from the player's last input
+    pub predicted: bool,
+}
+
+pub struct NetworkRollbackInputs<T> {
+    tick: u64,
+    inputs: HashMap<NetworkPlayer, NetworkRollbackInput<T>>,
+}
+
+impl<T> Default for NetworkRollbackInputs<T> {
+    fn default() -> Self {
+        Self {
+            tick: 0,
+            inputs: HashMap::new(),
+        }
+    }
+}
+
+impl<T> NetworkRollbackInputs<T> {
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn get(&self, player: NetworkPlayer) -> Option<&T> {
+        self.inputs.get(&player).map(|input| &input.data)
+    }
+
+    pub fn get_input(&self, player: NetworkPlayer) -> Option<&NetworkRollbackInput<T>> {
+        self.inputs.get(&player)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&NetworkPlayer, &NetworkRollbackInput<T>)> {
+        self.inputs.iter()
+    }
+}
+
+// systems that make up the simulation, run once for every (re)simulated tick
+pub struct NetworkRollbackStage(pub(crate) SystemStage);
+
+impl Default for NetworkRollbackStage {
+    fn default() -> Self {
+        Self(SystemStage::single_threaded())
+    }
+}
+
+struct NetworkRollbackSnapshot {
+    // every entity with registered rollback state, spawns and despawns can't be restored
+    entities: HashSet<Entity>,
+    state: Vec<Box<dyn Any + Send + Sync>>,
+}
+
+type NetworkRollbackTickInputs = HashMap<NetworkPlayer, (NetworkSerializedStruct, bool)>;
+type NetworkRollbackSaveFn = Box<dyn Fn(&mut World) -> Box<dyn Any + Send + Sync> + Send + Sync>;
+type NetworkRollbackLoadFn = Box<dyn Fn(&mut World, &(dyn Any + Send + Sync)) + Send + Sync>;
+type NetworkRollbackChecksumFn = Box<dyn Fn(&mut World, &mut DefaultHasher) + Send + Sync>;
+type NetworkRollbackEntitiesFn = Box<dyn Fn(&mut World) -> Vec<Entity> + Send + Sync>;
+type NetworkRollbackSendToWorldFn =
+    Box<dyn Fn(&mut World, u64, &NetworkRollbackTickInputs) + Send + Sync>;
+
+pub struct NetworkRegistryRollbackState {
+    pub(crate) save: NetworkRollbackSaveFn,
+    pub(crate) load: NetworkRollbackLoadFn,
+    pub(crate) checksum: NetworkRollbackChecksumFn,
+    pub(crate) entities: NetworkRollbackEntitiesFn,
+}
+
+impl NetworkRegistryRollbackState {
+    pub(crate) fn component<T>() -> Self
+    where
+        T: NetworkRollbackComponentTraits,
+    {
+        Self {
+            save: Box::new(|world: &mut World| {
+                let mut query = world.query::<(Entity, &T)>();
+                let saved: Vec<(Entity, T)> = query
+                    .iter(world)
+                    .map(|(entity, component)| (entity, component.clone()))
+                    .collect();
+                Box::new(saved)
+            }),
+            load: Box::new(|world: &mut World, saved: &(dyn Any + Send + Sync)| {
+                let saved = saved.downcast_ref::<Vec<(Entity, T)>>().unwrap();
+                let mut query = world.query_filtered::<Entity, With<T>>();
+                let existing: Vec<Entity> = query.iter(world).collect();
+                for entity in existing {
+                    if !saved
+                        .iter()
+                        .any(|(saved_entity, _)| *saved_entity == entity)
+                    {
+                        world.entity_mut(entity).remove::<T>();
+                    }
+                }
+                for (entity, component) in saved.iter() {
+                    if let Some(mut entity) = world.get_entity_mut(*entity) {
+                        entity.insert(component.clone());
+                    }
+                }
+            }),
+            checksum: Box::new(|world: &mut World, hasher: &mut DefaultHasher| {
+                let mut query = world.query::<(Entity, &T)>();
+                let mut components: Vec<(Entity, String)> = query
+                    .iter(world)
+                    .map(|(entity, component)| (entity, serialize(component)))
+                    .collect();
+                components.sort_by_key(|(entity, _)| *entity);
+                for (entity, component) in components {
+                    entity.hash(hasher);
+                    component.hash(hasher);
+                }
+            }),
+            entities: Box::new(|world: &mut World| {
+                let mut query = world.query_filtered::<Entity, With<T>>();
+                query.iter(world).collect()
+            }),
+        }
+    }
+
+    pub(crate) fn resource<T>() -> Self
+    where
+        T: NetworkRollbackResourceTraits,
+    {
+        Self {
+            save: Box::new(|world: &mut World| Box::new(world.get_resource::<T>().cloned())),
+            load: Box::new(|world: &mut World, saved: &(dyn Any + Send + Sync)| {
+                let saved = saved.downcast_ref::<Option<T>>().unwrap();
+                if let Some(resource) = saved {
+                    world.insert_resource(resource.clone());
+                } else {
+                    world.remove_resource::<T>();
+                }
+            }),
+            checksum: Box::new(|world: &mut World, hasher: &mut DefaultHasher| {
+                if let Some(resource) = world.get_resource::<T>() {
+                    serialize(resource).hash(hasher);
+                }
+            }),
+            entities: Box::new(|_: &mut World| vec![]),
+        }
+    }
+}
+
+pub struct NetworkRegistryRollbackInput {
+    pub(crate) default: Box<dyn Fn() -> NetworkSerializedStruct + Send + Sync>,
+    pub(crate) send_to_world: NetworkRollbackSendToWorldFn,
+}
+
+impl NetworkRegistryRollbackInput {
+    pub(crate) fn new<T>() -> Self
+    where
+        T: NetworkRollbackInputTraits,
+    {
+        Self {
+            default: Box::new(|| NetworkSerializedStruct::from_struct(&T::default())),
+            send_to_world: Box::new(
+                |world: &mut World, tick: u64, inputs: &NetworkRollbackTickInputs| {
+                    let mut rollback_inputs = world
+                        .get_resource_mut::<NetworkRollbackInputs<T>>()
+                        .unwrap();
+                    rollback_inputs.tick = tick;
+                    rollback_inputs.inputs.clear();
+                    for (player, (input, predicted)) in inputs.iter() {
+                        if let Some(data) = input.to_struct::<T>() {
+                            rollback_inputs.inputs.insert(
+                                *player,
+                                NetworkRollbackInput {
+                                    data,
+                                    predicted: *predicted,
+                                },
+                            );
+                        }
+                    }
+                },
+            ),
+        }
+    }
+}
+
+// server side bookkeeping, the server only relays rollback inputs so it just follows the newest tick
+#[derive(Default)]
+pub(crate) struct NetworkRollbackServer {
+    next_tick: u64,
+    // the first tick each player takes part in
+    participants: HashMap<NetworkPlayer, u64>,
+}
+
+impl NetworkRollbackServer {
+    pub(crate) fn receive(&mut self, tick: u64) {
+        self.next_tick = self.next_tick.max(tick + 1);
+    }
+
+    // players that haven't been seen yet take part starting with the next tick
+    pub(crate) fn add_player(&mut self, player: NetworkPlayer) -> Option<u64> {
+        if self.participants.contains_key(&player) {
+            None
+        } else {
+            self.participants.insert(player, self.next_tick);
+            Some(self.next_tick)
+        }
+    }
+
+    pub(crate) fn remove_player(&mut self, player: NetworkPlayer) {
+        self.participants.remove(&player);
+    }
+
+    pub(crate) fn participants(&self) -> impl Iterator<Item = (&NetworkPlayer, &u64)> {
+        self.participants.iter()
+    }
+}
+
+pub(crate) struct NetworkRollbackSession {
+    input_type: NetworkTypeName,
+    tick: u64,
+    pub(crate) next_input_tick: Option<u64>,
+    // the first tick each player takes part in, nothing is simulated until our own start arrives
+    participants: HashMap<NetworkPlayer, u64>,
+    inputs: BTreeMap<u64, HashMap<NetworkPlayer, NetworkSerializedStruct>>,
+    used_inputs: BTreeMap<u64, NetworkRollbackTickInputs>,
+    snapshots: BTreeMap<u64, NetworkRollbackSnapshot>,
+    rollback_to: Option<u64>,
+}
+
+impl NetworkRollbackSession {
+    pub(crate) fn new(input_type: NetworkTypeName) -> Self {
+        Self {
+            input_type,
+            tick: 0,
+            next_input_tick: None,
+            participants: HashMap::new(),
+            inputs: BTreeMap::new(),
+            used_inputs: BTreeMap::new(),
+            snapshots: BTreeMap::new(),
+            rollback_to: None,
+        }
+    }
+
+    pub(crate) fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub(crate) fn input_type(&self) -> &NetworkTypeName {
+        &self.input_type
+    }
+
+    // players that join late start at the server's newest tick instead of 0
+    pub(crate) fn start(&mut self, player: NetworkPlayer, tick: u64, me: NetworkPlayer) {
+        self.participants.insert(player, tick);
+        for (_, inputs) in self.inputs.range_mut(..tick) {
+            inputs.remove(&player);
+        }
+        if player == me && tick > self.tick {
+            self.tick = tick;
+            self.snapshots.clear();
+            self.used_inputs.clear();
+            self.rollback_to = None;
+        }
+        if player == me && self.next_input_tick.map_or(false, |next| next < tick) {
+            self.next_input_tick = None;
+        }
+    }
+
+    pub(crate) fn remove_player(&mut self, player: NetworkPlayer) {
+        self.participants.remove(&player);
+    }
+
+    fn participates(&self, player: NetworkPlayer, tick: u64) -> bool {
+        self.participants
+            .get(&player)
+            .map_or(false, |start| *start <= tick)
+    }
+
+    pub(crate) fn receive(
+        &mut self,
+        player: NetworkPlayer,
+        tick: u64,
+        input: NetworkSerializedStruct,
+    ) {
+        if input.type_name != self.input_type || tick + MAX_ROLLBACK_TICKS < self.tick {
+            return;
+        }
+        if self
+            .participants
+            .get(&player)
+            .map_or(false, |start| tick < *start)
+        {
+            return;
+        }
+        if let Some(used_inputs) = self.used_inputs.get(&tick) {
+            let mispredicted = if let Some((used_input, _)) = used_inputs.get(&player) {
+                used_input.data != input.data
+            } else {
+                true
+            };
+            if mispredicted {
+                self.rollback_to = Some(self.rollback_to.map_or(tick, |t| t.min(tick)));
+            }
+        }
+        self.inputs.entry(tick).or_default().insert(player, input);
+    }
+
+    fn last_confirmed_tick(&self, player: NetworkPlayer) -> Option<u64> {
+        self.inputs
+            .iter()
+            .rev()
+            .find(|(_, inputs)| inputs.contains_key(&player))
+            .map(|(tick, _)| *tick)
+    }
+
+    // the confirmed input if it arrived, otherwise the last input from that player is repeated
+    fn input(
+        &self,
+        player: NetworkPlayer,
+        tick: u64,
+        registry: &NetworkRegistry,
+    ) -> Option<(NetworkSerializedStruct, bool)> {
+        if let Some(input) = self.inputs.get(&tick).and_then(|i| i.get(&player)) {
+            Some((input.clone(), false))
+        } else if let Some(input) = self
+            .inputs
+            .range(..tick)
+            .rev()
+            .find_map(|(_, inputs)| inputs.get(&player))
+        {
+            Some((input.clone(), true))
+        } else {
+            self.default_input(registry).map(|input| (input, true))
+        }
+    }
+
+    // None when the input type isn't registered for rollback
+    pub(crate) fn default_input(
+        &self,
+        registry: &NetworkRegistry,
+    ) -> Option<NetworkSerializedStruct> {
+        registry
+            .entries()
+            .find(|(type_name, _)| **type_name == self.input_type)
+            .and_then(|(_, entry)| entry.rollback_input.as_ref())
+            .map(|input| (input.default)())
+    }
+
+    fn can_advance(&self, me: NetworkPlayer, players: &[NetworkPlayer]) -> bool {
+        let has_local_input = self
+            .inputs
+            .get(&self.tick)
+            .map_or(false, |inputs| inputs.contains_key(&me));
+        // don't predict too far ahead, the snapshots to roll back to would be gone
+        let within_prediction = players
+            .iter()
+            .filter(|player| self.participates(**player, self.tick))
+            .all(|player| {
+                let start = self.participants[player];
+                let confirmed = self
+                    .last_confirmed_tick(*player)
+                    .map_or(start, |tick| tick + 1);
+                self.tick < confirmed + MAX_ROLLBACK_TICKS
+            });
+        self.participates(me, self.tick) && has_local_input && within_prediction
+    }
+
+    fn simulate(
+        &mut self,
+        players: &[NetworkPlayer],
+        registry: &NetworkRegistry,
+        world: &mut World,
+    ) {
+        let tick = self.tick;
+        self.snapshots.insert(tick, save(registry, world));
+        let inputs: NetworkRollbackTickInputs = players
+            .iter()
+            .filter(|player| self.participates(**player, tick))
+            .filter_map(|player| Some((*player, self.input(*player, tick, registry)?)))
+            .collect();
+        if let Some((_, entry)) = registry
+            .entries()
+            .find(|(type_name, _)| **type_name == self.input_type)
+        {
+            if let Some(rollback_input) = &entry.rollback_input {
+                (rollback_input.send_to_world)(world, tick, &inputs);
+            }
+        }
+        self.used_inputs.insert(tick, inputs);
+        world.resource_scope(|world, mut stage: Mut<NetworkRollbackStage>| {
+            stage.0.run(world);
+        });
+        self.tick += 1;
+    }
+
+    fn resimulate_from(
+        &mut self,
+        tick: u64,
+        players: &[NetworkPlayer],
+        registry: &NetworkRegistry,
+        world: &mut World,
+    ) {
+        let end_tick = self.tick;
+        if let Some(snapshot) = self.snapshots.get(&tick) {
+            // an entity was spawned or despawned since, the simulation can't be rewound so the
+            // state stays diverged
+            if snapshot.entities != entities(registry, world) {
+                let mut events = world
+                    .get_resource_mut::<Events<NetworkDesyncEvent>>()
+                    .unwrap();
+                events.send(NetworkDesyncEvent {
+                    tick,
+                    checksums: vec![],
+                });
+                return;
+            }
+            load(registry, world, snapshot);
+            self.tick = tick;
+            while self.tick < end_tick {
+                self.simulate(players, registry, world);
+            }
+        }
+    }
+
+    fn prune(&mut self) {
+        let oldest_tick = self.tick.saturating_sub(MAX_ROLLBACK_TICKS);
+        self.snapshots = self.snapshots.split_off(&oldest_tick);
+        self.used_inputs = self.used_inputs.split_off(&oldest_tick);
+        // the newest input before the window is still needed to predict from
+        let keep_from = self
+            .inputs
+            .range(..oldest_tick)
+            .next_back()
+            .map_or(oldest_tick, |(tick, _)| *tick);
+        self.inputs = self.inputs.split_off(&keep_from);
+    }
+}
+
+fn save(registry: &NetworkRegistry, world: &mut World) -> NetworkRollbackSnapshot {
+    let mut state = vec![];
+    for (_, entry) in registry.entries() {
+        if let Some(rollback_state) = &entry.rollback_state {
+            state.push((rollback_state.save)(world));
+        }
+    }
+    NetworkRollbackSnapshot {
+        entities: entities(registry, world),
+        state,
+    }
+}
+
+fn entities(registry: &NetworkRegistry, world: &mut World) -> HashSet<Entity> {
+    let mut entities = HashSet::new();
+    for (_, entry) in registry.entries() {
+        if let Some(rollback_state) = &entry.rollback_state {
+            entities.extend((rollback_state.entities)(world));
+        }
+    }
+    entities
+}
+
+fn load(registry: &NetworkRegistry, world: &mut World, snapshot: &NetworkRollbackSnapshot) {
+    let mut saved = snapshot.state.iter();
+    for (_, entry) in registry.entries() {
+        if let Some(rollback_state) = &entry.rollback_state {
+            (rollback_state.load)(world, saved.next().unwrap().as_ref());
+        }
+    }
+}
+
+fn checksum(registry: &NetworkRegistry, world: &mut World) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let mut entries: Vec<(&NetworkTypeName, _)> = registry.entries().collect();
+    entries.sort_by_key(|(type_name, _)| *type_name);
+    for (_, entry) in entries {
+        if let Some(rollback_state) = &entry.rollback_state {
+            (rollback_state.checksum)(world, &mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+pub fn update_rollback(world: &mut World) {
+    world.resource_scope(|world, mut network: Mut<Network>| {
+        let Network {
+            state, registry, ..
+        } = &mut *network;
+        let client = if let Some(client) = state.client_mut() {
+            client
+        } else {
+            return;
+        };
+        let me = client.me;
+        let mut players = client.players();
+        if !players.contains(&me) {
+            players.push(me);
+        }
+        let remote_players: Vec<NetworkPlayer> =
+            players.iter().copied().filter(|p| *p != me).collect();
+        let sync_test = client.rollback_sync_test;
+        let session = if let Some(session) = &mut client.rollback {
+            session
+        } else {
+            return;
+        };
+        if let Some(rollback_to) = session.rollback_to.take() {
+            session.resimulate_from(rollback_to, &players, registry, world);
+        }
+        while session.can_advance(me, &remote_players) {
+            session.simulate(&players, registry, world);
+            if let Some(frames) = sync_test {
+                // roll back and resimulate right away, the state should come out the same
+                if session.tick() >= frames {
+                    let expected = checksum(registry, world);
+                    session.resimulate_from(session.tick() - frames, &players, registry, world);
+                    if checksum(registry, world) != expected {
+                        let mut events = world
+                            .get_resource_mut::<Events<NetworkSyncTestMismatchEvent>>()
+                            .unwrap();
+                        events.send(NetworkSyncTestMismatchEvent {
+                            tick: session.tick() - 1,
+                        });
+                    }
+                }
+            }
+        }
+        session.prune();
+    });
+}
@@ -1,5 +1,7 @@
+use crate::entity_map::UNMAPPED_ENTITY;
 use crate::network_type_name::NetworkTypeName;
-use crate::serializer::{deserialize, serialize};
+use crate::serializer::{serialize, try_deserialize};
+use bevy::log::warn;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -21,14 +23,25 @@ impl NetworkSerializedStruct {
         }
     }
 
+    // None if the type doesn't match or the data can't be deserialized on this side, only data
+    // referring to entities this side doesn't know is dropped silently
     pub fn to_struct<T>(&self) -> Option<T>
     where
         T: DeserializeOwned,
     {
-        if self.type_name == NetworkTypeName::of::<T>() {
-            Some(deserialize(&self.data))
-        } else {
-            None
+        if self.type_name != NetworkTypeName::of::<T>() {
+            return None;
+        }
+        match try_deserialize(&self.data) {
+            Ok(data) => Some(data),
+            Err(error) if error == UNMAPPED_ENTITY => None,
+            Err(error) => {
+                warn!(
+                    "dropped {:?} that failed to deserialize: {}",
+                    self.type_name, error
+                );
+                None
+            }
         }
     }
 }
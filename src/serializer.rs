@@ -1,4 +1,4 @@
-use ron::{de::from_str, ser::to_string};
+use ron::{de::from_str, error::ErrorCode, ser::to_string};
 use serde::{Deserialize, Serialize};
 
 // use these serializers everywhere, in case we want to swap them out eventually
@@ -12,6 +12,17 @@ where
     from_str(string).unwrap()
 }
 
+// errors raised by the (de)serialize impls themselves come back as just their message
+pub(crate) fn try_deserialize<'a, T>(string: &'a str) -> Result<T, String>
+where
+    T: Deserialize<'a>,
+{
+    from_str(string).map_err(|error| match error.code {
+        ErrorCode::Message(message) => message,
+        _ => error.to_string(),
+    })
+}
+
 pub(crate) fn serialize<T>(data: &T) -> String
 where
     T: ?Sized + Serialize,
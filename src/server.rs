@@ -1,13 +1,28 @@
 use crate::{
-    entity::NetworkEntity,
-    events::NetworkEventTraits,
+    bandwidth::{NetworkBandwidth, NetworkBandwidthStats},
+    entity::{NetworkEntity, NetworkOwnerLeavePolicy, NetworkOwnershipPolicy},
+    events::{NetworkDeferredEvent, NetworkEventTraits},
+    input::NetworkInputBuffers,
+    interest::NetworkSpatialInterest,
+    lag_compensation::{NetworkLagCompensation, NetworkLagCompensationTraits},
+    lockstep::{NetworkLockstepChecksums, NetworkLockstepServers},
     messages::NetworkMessage,
+    network_type_name::NetworkTypeName,
     player::NetworkPlayer,
     relevancy::NetworkRelevancy,
+    rollback::NetworkRollbackServer,
     serialized_struct::{NetworkSerializedStruct, NetworkSerializedStructMap},
+    update_rate::NetworkUpdateRate,
 };
 use bevy_nety_protocol::{NetworkHost, NetworkSocket};
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Instant,
+};
+
+// entities set up before they're spawned and spawn requests nobody answered are dropped after
+// this many ticks
+pub(crate) const PENDING_TIMEOUT_TICKS: u64 = 600;
 
 pub(crate) struct NetworkServerJoiner {
     pub(crate) socket: Option<NetworkSocket>,
@@ -18,6 +33,14 @@ pub(crate) struct NetworkServerPlayer {
     pub(crate) handle: NetworkPlayer,
     pub(crate) socket: NetworkSocket,
     pub(crate) data: NetworkSerializedStructMap,
+    pub(crate) rtt: f64,
+    pub(crate) interpolation_delay: f64,
+    pub(crate) inputs: NetworkInputBuffers,
+    // the last value of each network resource sent to the player
+    pub(crate) resources: HashMap<NetworkTypeName, NetworkSerializedStruct>,
+    // every resource was sent once, after that only changed resources are looked at
+    pub(crate) resources_sent: bool,
+    pub(crate) bandwidth: NetworkBandwidth,
 }
 
 pub(crate) struct NetworkServerEntity {
@@ -26,6 +49,34 @@ pub(crate) struct NetworkServerEntity {
     pub(crate) owner_changed: bool,
     pub(crate) owner: Option<NetworkPlayer>,
     pub(crate) last_owner: Option<NetworkPlayer>,
+    pub(crate) initialized: bool,
+    pub(crate) prefab: Option<NetworkSerializedStruct>,
+    pub(crate) ownership_policy: NetworkOwnershipPolicy,
+    pub(crate) owner_leave_policy: NetworkOwnerLeavePolicy,
+    // the player the entity is held for, and the server time the reservation ends
+    pub(crate) reserved: Option<(NetworkPlayer, f64)>,
+    // the tick the entry was made, for entries that are never spawned
+    pub(crate) created: u64,
+    pub(crate) parent: Option<NetworkEntity>,
+    pub(crate) parent_changed: bool,
+    pub(crate) follow_parent_relevancy: bool,
+}
+
+impl NetworkServerEntity {
+    pub(crate) fn set_owner(&mut self, owner: Option<NetworkPlayer>) {
+        if !self.owner_changed {
+            self.last_owner = self.owner;
+        }
+        self.owner = owner;
+        self.owner_changed = true;
+    }
+}
+
+pub(crate) enum NetworkServerEventTarget {
+    All,
+    AllExceptLocal,
+    Players(Vec<NetworkPlayer>),
+    Entity(NetworkEntity),
 }
 
 pub struct NetworkServer {
@@ -36,6 +87,22 @@ pub struct NetworkServer {
     pub(crate) entities: HashMap<NetworkEntity, NetworkServerEntity>,
     pub(crate) relevancy: NetworkRelevancy,
     pub(crate) entity_messages: VecDeque<(NetworkEntity, NetworkMessage)>,
+    pub(crate) started: Instant,
+    pub(crate) tick: u64,
+    pub(crate) lag_compensation: NetworkLagCompensation,
+    pub(crate) lockstep: NetworkLockstepServers,
+    pub(crate) lockstep_checksums: NetworkLockstepChecksums,
+    pub(crate) rollback: NetworkRollbackServer,
+    pub(crate) ownership_requests: Vec<(NetworkPlayer, NetworkEntity)>,
+    // the tick each request arrived
+    pub(crate) spawn_requests: HashMap<(NetworkPlayer, NetworkEntity), u64>,
+    // events sent since the last update, serialized once the new entities are known
+    pub(crate) deferred_events: Vec<(NetworkServerEventTarget, NetworkDeferredEvent)>,
+    // read from the world every update, entities without one send updates every tick
+    pub(crate) update_rates: HashMap<NetworkEntity, NetworkUpdateRate>,
+    // entities whose owner changed since the owner components were last updated
+    pub(crate) owner_changes: HashSet<NetworkEntity>,
+    pub(crate) owners_synced: bool,
 }
 
 impl NetworkServer {
@@ -48,82 +115,153 @@ impl NetworkServer {
             entities: HashMap::new(),
             relevancy: NetworkRelevancy::default(),
             entity_messages: VecDeque::default(),
+            started: Instant::now(),
+            tick: 0,
+            lag_compensation: NetworkLagCompensation::default(),
+            lockstep: NetworkLockstepServers::new(),
+            lockstep_checksums: NetworkLockstepChecksums::default(),
+            rollback: NetworkRollbackServer::default(),
+            ownership_requests: vec![],
+            spawn_requests: HashMap::new(),
+            deferred_events: vec![],
+            update_rates: HashMap::new(),
+            owner_changes: HashSet::new(),
+            owners_synced: false,
         }
     }
 
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    // seconds since the server started
+    pub fn time(&self) -> f64 {
+        self.started.elapsed().as_secs_f64()
+    }
+
+    // events are serialized and sent during the next network update rather than right away, after
+    // the entities spawned this frame, so their entity fields can refer to those entities
     pub fn send_to_all<T>(&mut self, event: T)
     where
         T: NetworkEventTraits,
     {
-        for player in self.players.iter_mut() {
-            player.socket.send(
-                NetworkMessage::Event {
-                    data: NetworkSerializedStruct::from_struct(&event),
-                }
-                .serialize(),
-            );
-        }
+        self.defer_event(NetworkServerEventTarget::All, event);
     }
 
+    // sent during the next network update, like send_to_all
     pub fn send_to_all_except_local<T>(&mut self, event: T)
     where
         T: NetworkEventTraits,
     {
-        for player in self.players.iter_mut() {
-            let is_local_player = if let Some(local_player) = self.local_player {
-                player.handle == local_player
-            } else {
-                false
-            };
-            if !is_local_player {
-                player.socket.send(
-                    NetworkMessage::Event {
-                        data: NetworkSerializedStruct::from_struct(&event),
-                    }
-                    .serialize(),
-                );
-            }
-        }
+        self.defer_event(NetworkServerEventTarget::AllExceptLocal, event);
     }
 
+    // sent during the next network update, like send_to_all
     pub fn send_to_players<T>(&mut self, players: &Vec<NetworkPlayer>, event: T)
     where
         T: NetworkEventTraits,
     {
-        for player in self.players.iter_mut() {
-            if players.contains(&player.handle) {
-                player.socket.send(
-                    NetworkMessage::Event {
-                        data: NetworkSerializedStruct::from_struct(&event),
-                    }
-                    .serialize(),
-                );
-            }
-        }
+        self.defer_event(NetworkServerEventTarget::Players(players.clone()), event);
     }
 
+    // sent during the next network update, like send_to_all
     pub fn send_to_entity<T>(&mut self, entity: NetworkEntity, event: T)
     where
         T: NetworkEventTraits,
     {
-        self.entity_messages.push_back((
-            entity,
-            NetworkMessage::EntityEvent {
-                entity,
-                from: None,
-                data: NetworkSerializedStruct::from_struct(&event),
-            },
+        self.defer_event(NetworkServerEventTarget::Entity(entity), event);
+    }
+
+    fn defer_event<T>(&mut self, target: NetworkServerEventTarget, event: T)
+    where
+        T: NetworkEventTraits,
+    {
+        self.deferred_events.push((
+            target,
+            Box::new(move || NetworkSerializedStruct::from_struct(&event)),
         ));
     }
 
+    pub(crate) fn send_event(
+        &mut self,
+        target: NetworkServerEventTarget,
+        data: NetworkSerializedStruct,
+    ) {
+        if let NetworkServerEventTarget::Entity(entity) = target {
+            self.entity_messages.push_back((
+                entity,
+                NetworkMessage::EntityEvent {
+                    entity,
+                    from: None,
+                    data,
+                },
+            ));
+            return;
+        }
+        let message = NetworkMessage::Event { data }.serialize_shared();
+        for player in self.players.iter_mut() {
+            let send = match &target {
+                NetworkServerEventTarget::All => true,
+                NetworkServerEventTarget::AllExceptLocal => {
+                    self.local_player != Some(player.handle)
+                }
+                NetworkServerEventTarget::Players(players) => players.contains(&player.handle),
+                NetworkServerEventTarget::Entity(_) => false,
+            };
+            if send {
+                player.socket.send_shared(message.clone());
+            }
+        }
+    }
+
+    pub fn player_rtt(&self, player: NetworkPlayer) -> Option<f64> {
+        self.players
+            .iter()
+            .find(|p| p.handle == player)
+            .map(|p| p.rtt)
+    }
+
+    pub fn player_interpolation_delay(&self, player: NetworkPlayer) -> Option<f64> {
+        self.players
+            .iter()
+            .find(|p| p.handle == player)
+            .map(|p| p.interpolation_delay)
+    }
+
+    // the tick the player was looking at when they acted, accounting for latency and interpolation
+    pub fn player_view_tick(&self, player: NetworkPlayer) -> Option<u64> {
+        if let Some(player) = self.players.iter().find(|p| p.handle == player) {
+            let view_time = self.time() - player.rtt / 2. - player.interpolation_delay;
+            self.lag_compensation.tick_at_time(view_time)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_lag_compensation_history(&mut self, ticks: u64) {
+        self.lag_compensation.set_history_length(ticks);
+    }
+
+    pub fn component_at_tick<T>(&self, entity: NetworkEntity, tick: u64) -> Option<&T>
+    where
+        T: NetworkLagCompensationTraits,
+    {
+        self.lag_compensation.get::<T>(entity, tick)
+    }
+
     pub(crate) fn players(&self) -> Vec<NetworkPlayer> {
         self.players.iter().map(|p| p.handle).collect()
     }
 
+    fn has_player(&self, player: NetworkPlayer) -> bool {
+        self.players.iter().any(|p| p.handle == player)
+    }
+
     pub(crate) fn get_or_insert_entity(
         &mut self,
         entity: NetworkEntity,
     ) -> &mut NetworkServerEntity {
+        let tick = self.tick;
         self.entities
             .entry(entity)
             .or_insert_with(|| NetworkServerEntity {
@@ -132,25 +270,259 @@ impl NetworkServer {
                 owner: None,
                 owner_changed: false,
                 last_owner: None,
+                initialized: false,
+                prefab: None,
+                ownership_policy: NetworkOwnershipPolicy::default(),
+                owner_leave_policy: NetworkOwnerLeavePolicy::default(),
+                reserved: None,
+                created: tick,
+                parent: None,
+                parent_changed: false,
+                follow_parent_relevancy: true,
             })
     }
 
+    // relevancy set for an entity that never shows up is dropped when it times out, players that
+    // aren't connected are ignored
     pub fn set_entity_relevant(
         &mut self,
         entity: NetworkEntity,
         player: NetworkPlayer,
         relevant: bool,
     ) {
-        self.relevancy.set_relevant(player, entity, relevant);
+        if self.has_player(player) {
+            self.get_or_insert_entity(entity);
+            self.relevancy.set_relevant(player, entity, relevant);
+        }
+    }
+
+    pub fn set_spatial_interest(&mut self, spatial_interest: Option<NetworkSpatialInterest>) {
+        self.relevancy.set_spatial_interest(spatial_interest);
+    }
+
+    // the entity a player sees the world from, used by spatial interest
+    pub fn set_player_viewpoint(&mut self, player: NetworkPlayer, entity: Option<NetworkEntity>) {
+        if self.has_player(player) {
+            self.relevancy.set_viewpoint(player, entity);
+        }
+    }
+
+    // an entity added to any group is only relevant to players in one of its groups
+    pub fn add_player_to_group(&mut self, player: NetworkPlayer, group: &str) {
+        if self.has_player(player) {
+            self.relevancy.set_player_group(player, group, true);
+        }
+    }
+
+    pub fn remove_player_from_group(&mut self, player: NetworkPlayer, group: &str) {
+        if self.has_player(player) {
+            self.relevancy.set_player_group(player, group, false);
+        }
+    }
+
+    pub fn add_entity_to_group(&mut self, entity: NetworkEntity, group: &str) {
+        self.get_or_insert_entity(entity);
+        self.relevancy.set_entity_group(entity, group, true);
+    }
+
+    pub fn remove_entity_from_group(&mut self, entity: NetworkEntity, group: &str) {
+        self.get_or_insert_entity(entity);
+        self.relevancy.set_entity_group(entity, group, false);
+    }
+
+    // relevancy is only evaluated again when something the server tracks changes, call these when
+    // something a relevancy rule reads has changed
+    pub fn refresh_entity_relevancy(&mut self, entity: NetworkEntity) {
+        self.relevancy.set_entity_dirty(entity);
+    }
+
+    pub fn refresh_player_relevancy(&mut self, player: NetworkPlayer) {
+        self.relevancy.set_player_dirty(player);
+    }
+
+    // children are only relevant while their parent is, unless this is turned off, their own
+    // relevancy still applies on top
+    pub fn set_entity_follow_parent_relevancy(&mut self, entity: NetworkEntity, follow: bool) {
+        self.get_or_insert_entity(entity).follow_parent_relevancy = follow;
+        self.relevancy.set_entity_dirty(entity);
+    }
+
+    // caps the bytes of entity updates sent to the player each tick, the rest wait for later ticks
+    pub fn set_player_bandwidth_budget(&mut self, player: NetworkPlayer, budget: Option<usize>) {
+        if let Some(player) = self.players.iter_mut().find(|p| p.handle == player) {
+            player.bandwidth.set_budget(budget);
+        }
+    }
+
+    // only players with a bandwidth budget have stats
+    pub fn player_bandwidth_stats(&self, player: NetworkPlayer) -> Option<NetworkBandwidthStats> {
+        self.players
+            .iter()
+            .find(|p| p.handle == player)
+            .and_then(|player| player.bandwidth.stats())
+    }
+
+    // how many player and entity pairs the server keeps relevancy for
+    pub fn relevancy_entry_count(&self) -> usize {
+        self.relevancy.entry_count()
     }
 
     pub fn set_entity_owner(&mut self, entity: NetworkEntity, owner: Option<NetworkPlayer>) {
-        let entity = self.get_or_insert_entity(entity);
-        if !entity.owner_changed {
-            entity.last_owner = entity.owner;
+        self.get_or_insert_entity(entity).set_owner(owner);
+        self.owner_changes.insert(entity);
+    }
+
+    pub fn set_entity_ownership_policy(
+        &mut self,
+        entity: NetworkEntity,
+        policy: NetworkOwnershipPolicy,
+    ) {
+        self.get_or_insert_entity(entity).ownership_policy = policy;
+    }
+
+    pub fn set_entity_owner_leave_policy(
+        &mut self,
+        entity: NetworkEntity,
+        policy: NetworkOwnerLeavePolicy,
+    ) {
+        self.get_or_insert_entity(entity).owner_leave_policy = policy;
+    }
+
+    // gives entities reserved for a player that left to another player, usually the same
+    // person after reconnecting, returns the entities that were handed over
+    pub fn reclaim_reserved_entities(
+        &mut self,
+        old_player: NetworkPlayer,
+        new_player: NetworkPlayer,
+    ) -> Vec<NetworkEntity> {
+        let mut reclaimed = vec![];
+        for (handle, entity) in self.entities.iter_mut() {
+            if let Some((reserved_for, _)) = entity.reserved {
+                if reserved_for == old_player {
+                    entity.reserved = None;
+                    entity.set_owner(Some(new_player));
+                    reclaimed.push(*handle);
+                }
+            }
+        }
+        self.owner_changes.extend(reclaimed.iter().copied());
+        reclaimed
+    }
+
+    // the server spawns its own entity for a client spawned entity, usually with the same
+    // NetworkEntity, and the client is made its owner
+    pub fn confirm_spawn_request(
+        &mut self,
+        player: NetworkPlayer,
+        requested: NetworkEntity,
+        confirmed: NetworkEntity,
+    ) {
+        if self.respond_to_spawn_request(player, requested, Some(confirmed)) {
+            self.set_entity_owner(confirmed, Some(player));
+        }
+    }
+
+    pub fn reject_spawn_request(&mut self, player: NetworkPlayer, requested: NetworkEntity) {
+        self.respond_to_spawn_request(player, requested, None);
+    }
+
+    pub(crate) fn expire_spawn_requests(&mut self) {
+        let expired: Vec<(NetworkPlayer, NetworkEntity)> = self
+            .spawn_requests
+            .iter()
+            .filter(|(_, tick)| **tick + PENDING_TIMEOUT_TICKS <= self.tick)
+            .map(|(request, _)| *request)
+            .collect();
+        for (player, requested) in expired {
+            self.reject_spawn_request(player, requested);
+        }
+    }
+
+    fn respond_to_spawn_request(
+        &mut self,
+        player: NetworkPlayer,
+        requested: NetworkEntity,
+        confirmed: Option<NetworkEntity>,
+    ) -> bool {
+        if self.spawn_requests.remove(&(player, requested)).is_none() {
+            return false;
+        }
+        if let Some(player) = self.players.iter_mut().find(|p| p.handle == player) {
+            player.socket.send(
+                NetworkMessage::EntitySpawnResponse {
+                    requested,
+                    confirmed,
+                }
+                .serialize(),
+            );
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn approve_ownership_request(&mut self, player: NetworkPlayer, entity: NetworkEntity) {
+        self.respond_to_ownership_request(player, entity, true);
+    }
+
+    pub fn deny_ownership_request(&mut self, player: NetworkPlayer, entity: NetworkEntity) {
+        self.respond_to_ownership_request(player, entity, false);
+    }
+
+    fn respond_to_ownership_request(
+        &mut self,
+        player: NetworkPlayer,
+        entity: NetworkEntity,
+        approved: bool,
+    ) {
+        let request = self
+            .ownership_requests
+            .iter()
+            .position(|request| *request == (player, entity));
+        if let Some(request) = request {
+            self.ownership_requests.remove(request);
+        } else {
+            return;
+        }
+        let server_player =
+            if let Some(server_player) = self.players.iter_mut().find(|p| p.handle == player) {
+                server_player
+            } else {
+                return;
+            };
+        let approved = if let Some(server_entity) = self.entities.get_mut(&entity) {
+            if approved {
+                server_entity.set_owner(Some(player));
+                self.owner_changes.insert(entity);
+            }
+            approved
+        } else {
+            false
+        };
+        server_player.socket.send(
+            NetworkMessage::EntityOwnershipResponse {
+                entity,
+                release: false,
+                approved,
+            }
+            .serialize(),
+        );
+    }
+
+    pub fn entity_owner(&self, entity: NetworkEntity) -> Option<NetworkPlayer> {
+        self.entities.get(&entity).and_then(|entity| entity.owner)
+    }
+
+    // the entities whose owner changed since the last call, None the first time since every
+    // entity needs its owner component checked then
+    pub(crate) fn take_owner_changes(&mut self) -> Option<HashSet<NetworkEntity>> {
+        let changes = std::mem::take(&mut self.owner_changes);
+        if self.owners_synced {
+            Some(changes)
+        } else {
+            self.owners_synced = true;
+            None
         }
-        entity.owner = owner;
-        entity.owner_changed = true;
     }
 
     pub(crate) fn is_entity_owner(&mut self, entity: NetworkEntity) -> bool {
@@ -0,0 +1,185 @@
+use crate::{
+    network::Network, player::NetworkPlayer, resource::NetworkRegistryResource,
+    serialized_struct::NetworkSerializedStruct,
+};
+use bevy::{
+    app::Events,
+    ecs::schedule::{StateData, StateError},
+    prelude::*,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub trait NetworkStateTraits: StateData + Serialize + DeserializeOwned {}
+impl<T> NetworkStateTraits for T where T: StateData + Serialize + DeserializeOwned {}
+
+// a client tried to change a server driven state, the change is undone on the client and
+// server code decides whether to make it
+#[derive(Debug, Clone)]
+pub struct NetworkStateRequestEvent<S: NetworkStateTraits> {
+    pub player: NetworkPlayer,
+    pub state: S,
+}
+
+// a server state that couldn't be queued yet, it's retried every update
+struct NetworkPendingState<S: NetworkStateTraits>(S);
+
+// a server state queued on the client, kept until the state driver applies it
+struct NetworkQueuedState<S: NetworkStateTraits>(S);
+
+type NetworkStateCurrentFn = Box<dyn Fn(&World) -> Option<NetworkSerializedStruct> + Send + Sync>;
+type NetworkStateOverwriteFn = Box<dyn Fn(&mut World, NetworkSerializedStruct) + Send + Sync>;
+type NetworkStateRetryFn = Box<dyn Fn(&mut World) -> bool + Send + Sync>;
+type NetworkStateSendRequestFn =
+    Box<dyn Fn(&mut World, NetworkPlayer, NetworkSerializedStruct) + Send + Sync>;
+
+pub struct NetworkRegistryState {
+    pub(crate) current: NetworkStateCurrentFn,
+    pub(crate) overwrite: NetworkStateOverwriteFn,
+    // queues a pending server state again, true while it's still pending
+    pub(crate) retry: NetworkStateRetryFn,
+    pub(crate) send_request_to_world: NetworkStateSendRequestFn,
+}
+
+impl NetworkRegistryState {
+    pub(crate) fn new<S>() -> Self
+    where
+        S: NetworkStateTraits,
+    {
+        Self {
+            current: Box::new(current::<S>),
+            overwrite: Box::new(overwrite::<S>),
+            retry: Box::new(|world: &mut World| {
+                if let Some(NetworkPendingState(value)) =
+                    world.remove_resource::<NetworkPendingState<S>>()
+                {
+                    queue(world, value);
+                }
+                world.contains_resource::<NetworkPendingState<S>>()
+            }),
+            send_request_to_world: Box::new(
+                |world: &mut World, player: NetworkPlayer, data: NetworkSerializedStruct| {
+                    if let Some(state) = data.to_struct::<S>() {
+                        let mut events = world
+                            .get_resource_mut::<Events<NetworkStateRequestEvent<S>>>()
+                            .unwrap();
+                        events.send(NetworkStateRequestEvent { player, state });
+                    }
+                },
+            ),
+        }
+    }
+
+    // states are replicated like resources, holding the current state
+    pub(crate) fn resource<S>() -> NetworkRegistryResource
+    where
+        S: NetworkStateTraits,
+    {
+        NetworkRegistryResource {
+            serialize: Box::new(current::<S>),
+            changed: Box::new(|world: &World| {
+                world.is_resource_changed::<State<S>>() || !world.contains_resource::<State<S>>()
+            }),
+            filter: None,
+            insert: Box::new(overwrite::<S>),
+            remove: Box::new(|_: &mut World| {}),
+        }
+    }
+}
+
+fn current<S>(world: &World) -> Option<NetworkSerializedStruct>
+where
+    S: NetworkStateTraits,
+{
+    world
+        .get_resource::<State<S>>()
+        .map(|state| NetworkSerializedStruct::from_struct(state.current()))
+}
+
+fn overwrite<S>(world: &mut World, data: NetworkSerializedStruct)
+where
+    S: NetworkStateTraits,
+{
+    if let Some(value) = data.to_struct::<S>() {
+        world.remove_resource::<NetworkPendingState<S>>();
+        queue(world, value);
+    }
+}
+
+fn queue<S>(world: &mut World, value: S)
+where
+    S: NetworkStateTraits,
+{
+    if let Some(mut state) = world.get_resource_mut::<State<S>>() {
+        match state.overwrite_set(value.clone()) {
+            Ok(()) => world.insert_resource(NetworkQueuedState(value)),
+            Err(StateError::AlreadyInState) => {}
+            Err(_) => world.insert_resource(NetworkPendingState(value)),
+        }
+    } else {
+        world.insert_resource(State::new(value));
+    }
+}
+
+// runs at the end of PreUpdate and Update, right before the state driver gets to apply what was
+// scheduled. a change made on a client is undone before any on_exit or on_enter system runs and is
+// sent to the server as a request instead
+pub(crate) fn guard_client_state<S>(world: &mut World)
+where
+    S: NetworkStateTraits,
+{
+    let is_client_only = match world.get_resource_mut::<Network>() {
+        Some(mut network) => network.is_client() && !network.is_server(),
+        None => false,
+    };
+    if !is_client_only {
+        return;
+    }
+    let touched = if let Some(state) = world.get_resource::<State<S>>() {
+        // State doesn't expose its scheduled operation other than through Debug
+        !state.inactives().is_empty() || !format!("{:?}", state).contains(", scheduled: None,")
+    } else {
+        false
+    };
+    if !touched {
+        world.remove_resource::<NetworkQueuedState<S>>();
+        return;
+    }
+
+    let state = world.remove_resource::<State<S>>().unwrap();
+    let previous = state
+        .inactives()
+        .first()
+        .unwrap_or_else(|| state.current())
+        .clone();
+    let requested = settle(state).current().clone();
+
+    let mut state = settle(State::new(previous.clone()));
+    let mut expected = previous;
+    if let Some(NetworkQueuedState(value)) = world.get_resource::<NetworkQueuedState<S>>() {
+        if state.overwrite_set(value.clone()).is_ok() {
+            expected = value.clone();
+        }
+    }
+    world.insert_resource(state);
+
+    if requested != expected {
+        let mut network = world.get_resource_mut::<Network>().unwrap();
+        if let Some(client) = network.client_mut() {
+            client.send_state_request(NetworkSerializedStruct::from_struct(&requested));
+        }
+    }
+}
+
+// runs the state driver on its own until the state is done transitioning, without any of the app's
+// state systems
+fn settle<S>(state: State<S>) -> State<S>
+where
+    S: NetworkStateTraits,
+{
+    let mut world = World::new();
+    world.insert_resource(state);
+    SystemStage::single_threaded()
+        .with_system_set(State::<S>::get_driver())
+        .run(&mut world);
+    world.remove_resource::<State<S>>().unwrap()
+}
@@ -0,0 +1,240 @@
+use super::common::prelude::*;
+use crate::{
+    bandwidth::{NetworkBandwidth, MAX_PENDING_UPDATES},
+    prelude::*,
+};
+
+#[test]
+fn no_budget() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    for _ in 0..5 {
+        send_entity_event(&mut env, network_entity, "update");
+    }
+    env["server"].app().update();
+
+    assert!(env["server"]
+        .server()
+        .player_bandwidth_stats(client_me)
+        .is_none());
+    env.flush_network();
+    assert_eq!(received_entity_events(&mut env).len(), 5);
+}
+
+#[test]
+fn budget_defers_updates() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    for i in 0..5 {
+        send_entity_event(&mut env, network_entity, &i.to_string());
+    }
+    env["server"].app().update();
+
+    let stats = env["server"]
+        .server()
+        .player_bandwidth_stats(client_me)
+        .unwrap();
+    assert_eq!(stats.ticks, 1);
+    assert_eq!(stats.saturated_ticks, 1);
+    assert_eq!(stats.deferred_updates, 4);
+    assert!(stats.queued_bytes > 0);
+
+    env.flush_network();
+    assert_eq!(
+        received_entity_events(&mut env),
+        vec!["0", "1", "2", "3", "4"]
+    );
+    let stats = env["server"]
+        .server()
+        .player_bandwidth_stats(client_me)
+        .unwrap();
+    assert_eq!(stats.saturated_ticks, 4);
+    assert_eq!(stats.queued_bytes, 0);
+    assert!(stats.saturation() > 0.);
+}
+
+#[test]
+fn priority_component_goes_first() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let low = NetworkEntity::new();
+    let high = NetworkEntity::new();
+    env["server"].world().spawn().insert(low);
+    env["server"]
+        .world()
+        .spawn()
+        .insert(high)
+        .insert(NetworkPriority(10.));
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    send_entity_event(&mut env, low, "low");
+    send_entity_event(&mut env, high, "high");
+    env.flush_network();
+
+    assert_eq!(received_entity_events(&mut env), vec!["high", "low"]);
+}
+
+#[test]
+fn waiting_updates_gain_priority() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let low = NetworkEntity::new();
+    let high = NetworkEntity::new();
+    env["server"].world().spawn().insert(low);
+    env["server"]
+        .world()
+        .spawn()
+        .insert(high)
+        .insert(NetworkPriority(10.));
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    send_entity_event(&mut env, low, "low");
+    for _ in 0..20 {
+        send_entity_event(&mut env, high, "high");
+        env["server"].app().update();
+        env["client"].app().update();
+    }
+    env.flush_network();
+
+    assert!(received_entity_events(&mut env).contains(&"low".to_string()));
+    let low_index = received_entity_events(&mut env)
+        .iter()
+        .position(|foo| foo == "low")
+        .unwrap();
+    assert!(low_index > 0);
+}
+
+#[test]
+fn despawn_sends_queued_updates() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    for i in 0..5 {
+        send_entity_event(&mut env, network_entity, &i.to_string());
+    }
+    env["server"].app().update();
+    env["server"].world().despawn(entity);
+    env.flush_network();
+
+    let stats = env["server"]
+        .server()
+        .player_bandwidth_stats(client_me)
+        .unwrap();
+    assert_eq!(stats.queued_bytes, 0);
+    assert_eq!(
+        received_entity_events(&mut env),
+        vec!["0", "1", "2", "3", "4"]
+    );
+}
+
+#[test]
+fn irrelevant_sends_queued_updates() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    for i in 0..5 {
+        send_entity_event(&mut env, network_entity, &i.to_string());
+    }
+    env["server"].app().update();
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client_me, false);
+    env.flush_network();
+
+    assert_eq!(
+        received_entity_events(&mut env),
+        vec!["0", "1", "2", "3", "4"]
+    );
+    assert_eq!(
+        env["client"]
+            .introspect()
+            .entity_despawn_events
+            .last()
+            .unwrap()
+            .reason,
+        NetworkEntityDespawnReason::Irrelevant
+    );
+}
+
+#[test]
+fn queue_is_capped() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_player_bandwidth_budget(client_me, Some(1));
+    let count = MAX_PENDING_UPDATES + 10;
+    for i in 0..count {
+        send_entity_event(&mut env, network_entity, &i.to_string());
+    }
+    env["server"].app().update();
+
+    let stats = env["server"]
+        .server()
+        .player_bandwidth_stats(client_me)
+        .unwrap();
+    assert_eq!(stats.dropped_updates, 10);
+    for _ in 0..count {
+        env["server"].app().update();
+    }
+    env.flush_network();
+    let received = received_entity_events(&mut env);
+    assert_eq!(received.len(), MAX_PENDING_UPDATES);
+    assert_eq!(received[0], "10");
+    assert_eq!(received.last().unwrap(), &(count - 1).to_string());
+}
+
+#[test]
+fn rate_limited_entity_does_not_hold_others() {
+    let (mut socket, mut receiver) = pseudo_socket_pair();
+    let mut bandwidth = NetworkBandwidth::default();
+
+    let held = NetworkEntity::new();
+    let other = NetworkEntity::new();
+    bandwidth.send(&mut socket, held, "held 1".into(), true);
+    bandwidth.send(&mut socket, other, "other".into(), false);
+    // the entity's own updates wait behind the held one, even without a rate
+    bandwidth.send(&mut socket, held, "held 2".into(), false);
+    assert_eq!(receiver.receive().as_deref(), Some("other"));
+    assert!(receiver.receive().is_none());
+
+    bandwidth.flush(&mut socket, |_| None);
+    assert!(receiver.receive().is_none());
+    bandwidth.flush(&mut socket, |_| Some(1.));
+    assert_eq!(receiver.receive().as_deref(), Some("held 1"));
+    assert_eq!(receiver.receive().as_deref(), Some("held 2"));
+    assert!(!bandwidth.has_pending());
+}
@@ -0,0 +1,188 @@
+use super::common::prelude::*;
+use crate::{prelude::*, server::PENDING_TIMEOUT_TICKS};
+use bevy::prelude::*;
+
+fn setup(env: &mut TestEnvironment) -> (NetworkEntity, Entity) {
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["client1"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestPrefab { value: 4 })
+        .id();
+    env.flush_network();
+    (network_entity, entity)
+}
+
+#[test]
+fn spawn_request() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, _) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let introspect = env["server"].introspect();
+    assert_eq!(introspect.spawn_request_events.len(), 1);
+    let request = introspect.spawn_request_events[0].clone();
+    assert_eq!(request.player, client1_me);
+    assert_eq!(request.network_entity, network_entity);
+    assert_eq!(
+        request.prefab::<TestPrefab>(),
+        Some(TestPrefab { value: 4 })
+    );
+    assert!(env["client1"].client().is_entity_pending(network_entity));
+    assert_eq!(
+        env["client1"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+    assert!(find_entities(env["client2"].app(), network_entity).is_empty());
+}
+
+#[test]
+fn confirm() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"].world().spawn().insert(network_entity);
+    env["server"]
+        .server()
+        .confirm_spawn_request(client1_me, network_entity, network_entity);
+    env.flush_network();
+
+    let introspect = env["client1"].introspect();
+    assert_eq!(introspect.spawn_response_events.len(), 1);
+    assert_eq!(introspect.spawn_response_events[0].entity, entity);
+    assert_eq!(
+        introspect.spawn_response_events[0].confirmed,
+        Some(network_entity)
+    );
+    assert!(!env["client1"].client().is_entity_pending(network_entity));
+    assert_eq!(
+        find_entities(env["client1"].app(), network_entity),
+        vec![entity]
+    );
+    assert_eq!(find_entities(env["client2"].app(), network_entity).len(), 1);
+    assert_eq!(
+        env["client2"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+}
+
+#[test]
+fn remap() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let server_network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(server_network_entity);
+    env["server"]
+        .server()
+        .confirm_spawn_request(client1_me, network_entity, server_network_entity);
+    env.flush_network();
+
+    assert!(find_entities(env["client1"].app(), network_entity).is_empty());
+    assert_eq!(
+        find_entities(env["client1"].app(), server_network_entity),
+        vec![entity]
+    );
+    assert_eq!(
+        env["client1"].network().entity_owner(server_network_entity),
+        Some(client1_me)
+    );
+    assert!(env["client1"]
+        .world()
+        .get::<NetworkEntityOwner>(entity)
+        .is_some());
+}
+
+#[test]
+fn confirm_before_spawn() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .confirm_spawn_request(client1_me, network_entity, network_entity);
+    env.flush_network();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        find_entities(env["client1"].app(), network_entity),
+        vec![entity]
+    );
+    assert_eq!(
+        env["client2"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+}
+
+#[test]
+fn reject() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .reject_spawn_request(client1_me, network_entity);
+    env.flush_network();
+
+    let introspect = env["client1"].introspect();
+    assert_eq!(introspect.spawn_response_events[0].confirmed, None);
+    assert_eq!(
+        introspect.entity_despawn_events[0].reason,
+        NetworkEntityDespawnReason::Rejected
+    );
+    assert!(env["client1"].world().get_entity(entity).is_none());
+    assert_eq!(env["server"].introspect().spawn_request_events.len(), 1);
+}
+
+#[test]
+fn confirmed_but_never_spawned() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, _) = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .confirm_spawn_request(client1_me, network_entity, network_entity);
+    env.flush_network();
+    assert!(env["server"]
+        .server()
+        .entities
+        .contains_key(&network_entity));
+
+    for _ in 0..PENDING_TIMEOUT_TICKS {
+        env["server"].app().update();
+    }
+    assert!(!env["server"]
+        .server()
+        .entities
+        .contains_key(&network_entity));
+}
+
+#[test]
+fn unanswered_request_expires() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env);
+
+    assert!(env["client1"].client().is_entity_pending(network_entity));
+    for _ in 0..PENDING_TIMEOUT_TICKS {
+        env["server"].app().update();
+    }
+    env.flush_network();
+
+    let introspect = env["client1"].introspect();
+    assert_eq!(introspect.spawn_response_events.len(), 1);
+    assert_eq!(introspect.spawn_response_events[0].confirmed, None);
+    assert!(env["client1"].world().get_entity(entity).is_none());
+    assert!(env["server"].server().spawn_requests.is_empty());
+}
@@ -0,0 +1,93 @@
+use super::common::prelude::*;
+use crate::clock::{NetworkClock, NetworkClockSample, NetworkClockSync};
+
+#[test]
+fn server_clock() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.flush_network();
+
+    let server_tick = env["server"].server().tick();
+    assert!(env["server"].clock().synchronized());
+    assert_eq!(env["server"].clock().server_tick(), server_tick);
+    assert_eq!(env["server"].clock().rtt(), 0.);
+}
+
+#[test]
+fn disconnected_clock() {
+    let mut env = TestEnvironment::default();
+
+    env.create_app("app");
+    env.flush_network();
+
+    assert!(!env["app"].clock().synchronized());
+}
+
+#[test]
+fn client_clock() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    assert!(!env["client"].clock().synchronized());
+    env.flush_network();
+
+    assert!(env["client"].clock().synchronized());
+    let server_tick = env["server"].server().tick();
+    let client_estimate = env["client"].clock().server_tick();
+    assert!(client_estimate + 2 >= server_tick);
+    // the tick rate is measured over updates microseconds apart, so extrapolating it can overshoot
+    // by a few ticks, the estimated time can't
+    assert!(client_estimate <= server_tick + 8);
+    assert!(env["client"].clock().server_time() <= env["server"].server().time());
+}
+
+#[test]
+fn clock_time_to_tick() {
+    let clock = NetworkClock {
+        synchronized: true,
+        server_time: 10.,
+        server_tick: 600,
+        server_tick_rate: 60.,
+        rtt: 0.,
+    };
+    assert_eq!(clock.server_time_to_tick(10.), 600);
+    assert_eq!(clock.server_time_to_tick(10.5), 630);
+    assert_eq!(clock.server_time_to_tick(9.), 540);
+    assert_eq!(clock.server_time_to_tick(-100.), 0);
+}
+
+#[test]
+fn server_client_clock() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server_client("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    assert!(env["server"].clock().synchronized());
+    assert!(env["client"].clock().synchronized());
+}
+
+#[test]
+fn clock_ignores_rtt_spikes() {
+    let mut sync = NetworkClockSync::new();
+    for i in 0..7 {
+        sync.add_sample(NetworkClockSample {
+            rtt: 0.05,
+            offset: 10.,
+            server_time: i as f64,
+            server_tick: i * 60,
+        });
+    }
+    sync.add_sample(NetworkClockSample {
+        rtt: 2.,
+        offset: 11.,
+        server_time: 7.,
+        server_tick: 7 * 60,
+    });
+    assert_eq!(sync.offset(), 10.);
+    assert_eq!(sync.rtt(), 0.05);
+    assert_eq!(sync.tick_rate(), 60.);
+}
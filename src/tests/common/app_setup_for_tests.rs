@@ -1,5 +1,8 @@
 use super::introspection::{Introspection, IntrospectionPlugin};
-use super::test_structs::{TestGameEvent, TestPlayerData};
+use super::test_structs::{
+    TestComponent, TestEntityRefEvent, TestGameEvent, TestGameState, TestInput, TestLinkPrefab,
+    TestPlayerData, TestPrefab, TestRollbackState, TestScore, TestSecret,
+};
 use crate::prelude::*;
 use bevy::prelude::*;
 
@@ -13,11 +16,28 @@ pub trait AppSetupForTests {
 impl AppSetupForTests for App {
     fn setup_for_tests(&mut self) -> &mut Self {
         self.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
             .add_plugin(NetworkPlugin)
             .add_plugin(IntrospectionPlugin)
             .add_network_event::<TestGameEvent>()
             .add_network_entity_event::<TestGameEvent>()
+            .add_network_event::<TestEntityRefEvent>()
+            .add_network_entity_event::<TestEntityRefEvent>()
             .add_network_player_data::<TestPlayerData>()
+            .add_network_resource::<TestScore>()
+            .add_state(TestGameState::Lobby)
+            .add_network_state::<TestGameState>()
+            .add_network_resource_filtered(|player, secret: &TestSecret| secret.player == player)
+            .add_network_lag_compensation::<TestComponent>()
+            .add_network_input::<TestInput>()
+            .add_network_lockstep::<TestInput>()
+            .init_resource::<TestRollbackState>()
+            .add_network_rollback::<TestInput>()
+            .add_network_rollback_resource::<TestRollbackState>()
+            .add_network_prefab(|entity, prefab: &TestPrefab| {
+                entity.insert(TestComponent(prefab.value));
+            })
+            .add_network_prefab(|_, _: &TestLinkPrefab| {})
     }
 
     fn network(&self) -> &Network {
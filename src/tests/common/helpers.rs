@@ -0,0 +1,74 @@
+use super::{
+    pseudo_network::PseudoNetwork, test_environment::TestEnvironment, test_structs::TestGameEvent,
+};
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_nety_protocol::{
+    NetworkConnectStatus, NetworkConnectorProtocol, NetworkHostProtocol, NetworkSocket,
+};
+
+// a server named "server" and a client named "client", returns the client's player
+pub fn setup_server_and_client(env: &mut TestEnvironment) -> NetworkPlayer {
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+    env["client"].network().me().unwrap()
+}
+
+// a server named "server" and clients named "client1" and "client2", returns their players
+pub fn setup_server_and_two_clients(env: &mut TestEnvironment) -> (NetworkPlayer, NetworkPlayer) {
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+    let client1_me = env["client1"].network().me().unwrap();
+    let client2_me = env["client2"].network().me().unwrap();
+    (client1_me, client2_me)
+}
+
+pub fn find_entities(app: &mut App, network_entity: NetworkEntity) -> Vec<Entity> {
+    let mut query = app.world.query::<(Entity, &NetworkEntity)>();
+    query
+        .iter(&app.world)
+        .filter(|(_, e)| **e == network_entity)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+pub fn find_entity(app: &mut App, network_entity: NetworkEntity) -> Option<Entity> {
+    find_entities(app, network_entity).first().copied()
+}
+
+pub fn has_entity(env: &mut TestEnvironment, name: &str, network_entity: NetworkEntity) -> bool {
+    find_entity(env[name].app(), network_entity).is_some()
+}
+
+// sends a TestGameEvent from the server to the entity
+pub fn send_entity_event(env: &mut TestEnvironment, network_entity: NetworkEntity, foo: &str) {
+    env["server"]
+        .server()
+        .send_to_entity(network_entity, TestGameEvent { foo: foo.into() });
+}
+
+// the TestGameEvent entity events "client" received, in order
+pub fn received_entity_events(env: &mut TestEnvironment) -> Vec<String> {
+    env["client"]
+        .introspect()
+        .test_entity_events
+        .iter()
+        .map(|event| event.data.foo.clone())
+        .collect()
+}
+
+// a connected socket pair outside of any app, the first is the host's side
+pub fn pseudo_socket_pair() -> (NetworkSocket, NetworkSocket) {
+    let mut pseudo_net = PseudoNetwork::new();
+    let mut host = pseudo_net.create_host();
+    let mut connector = pseudo_net.create_connector().as_success();
+    let connector_socket = if let NetworkConnectStatus::Connected(socket) = connector.status() {
+        socket
+    } else {
+        panic!("Failed to connect");
+    };
+    (host.accept().unwrap(), connector_socket)
+}
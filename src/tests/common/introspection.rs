@@ -1,4 +1,4 @@
-use super::test_structs::TestGameEvent;
+use super::test_structs::{TestEntityRefEvent, TestGameEvent, TestGameState, TestInput};
 use crate::{events::NetworkEntityEvent, prelude::*};
 use bevy::prelude::*;
 
@@ -8,7 +8,8 @@ impl Plugin for IntrospectionPlugin {
     fn build(&self, app: &mut App) {
         // TODO: at what stage should capture_events run?
         app.init_resource::<Introspection>()
-            .add_system(capture_events);
+            .add_system(capture_events)
+            .add_system(capture_entity_events);
     }
 }
 
@@ -22,6 +23,22 @@ pub struct Introspection {
     pub test_game_events_on_client: Vec<NetworkEvent<TestGameEvent>>,
     pub test_game_events_on_server: Vec<NetworkServerEvent<TestGameEvent>>,
     pub test_entity_events: Vec<NetworkEntityEvent<TestGameEvent>>,
+    pub lockstep_tick_events: Vec<NetworkLockstepTickEvent<TestInput>>,
+    pub desync_events: Vec<NetworkDesyncEvent>,
+    pub sync_test_mismatch_events: Vec<NetworkSyncTestMismatchEvent>,
+    pub entity_spawn_events: Vec<NetworkEntitySpawnEvent>,
+    pub entity_despawn_events: Vec<NetworkEntityDespawnEvent>,
+    pub entity_ownership_changed_events: Vec<NetworkEntityOwnershipChangedEvent>,
+    pub entity_orphaned_events: Vec<NetworkEntityOrphanedEvent>,
+    pub spawn_request_events: Vec<NetworkSpawnRequestEvent>,
+    pub spawn_response_events: Vec<NetworkSpawnResponseEvent>,
+    pub ownership_request_events: Vec<NetworkOwnershipRequestEvent>,
+    pub ownership_response_events: Vec<NetworkOwnershipResponseEvent>,
+    pub entity_ref_events_on_client: Vec<TestEntityRefEvent>,
+    pub entity_ref_events_on_server: Vec<TestEntityRefEvent>,
+    pub entity_ref_entity_events: Vec<(Entity, TestEntityRefEvent)>,
+    pub state_request_events: Vec<NetworkStateRequestEvent<TestGameState>>,
+    pub entity_event_dropped_events: Vec<NetworkEntityEventDroppedEvent>,
 }
 
 impl Introspection {
@@ -40,6 +57,12 @@ pub fn capture_events(
     mut test_game_events_on_client: EventReader<NetworkEvent<TestGameEvent>>,
     mut test_game_events_on_server: EventReader<NetworkServerEvent<TestGameEvent>>,
     mut test_entity_events: EventReader<NetworkEntityEvent<TestGameEvent>>,
+    mut lockstep_tick_events: EventReader<NetworkLockstepTickEvent<TestInput>>,
+    mut desync_events: EventReader<NetworkDesyncEvent>,
+    mut sync_test_mismatch_events: EventReader<NetworkSyncTestMismatchEvent>,
+    mut entity_spawn_events: EventReader<NetworkEntitySpawnEvent>,
+    mut entity_despawn_events: EventReader<NetworkEntityDespawnEvent>,
+    mut entity_ownership_changed_events: EventReader<NetworkEntityOwnershipChangedEvent>,
 ) {
     for event in connect_events.iter() {
         introspection.connect_events.push(event.clone());
@@ -76,4 +99,82 @@ pub fn capture_events(
             data: event.data.clone(),
         });
     }
+    for event in lockstep_tick_events.iter() {
+        introspection
+            .lockstep_tick_events
+            .push(NetworkLockstepTickEvent {
+                tick: event.tick,
+                inputs: event.inputs.clone(),
+            });
+    }
+    for event in desync_events.iter() {
+        introspection.desync_events.push(event.clone());
+    }
+    for event in sync_test_mismatch_events.iter() {
+        introspection.sync_test_mismatch_events.push(event.clone());
+    }
+    for event in entity_spawn_events.iter() {
+        introspection.entity_spawn_events.push(event.clone());
+    }
+    for event in entity_despawn_events.iter() {
+        introspection.entity_despawn_events.push(event.clone());
+    }
+    for event in entity_ownership_changed_events.iter() {
+        introspection
+            .entity_ownership_changed_events
+            .push(event.clone());
+    }
+}
+
+pub fn capture_entity_events(
+    mut introspection: ResMut<Introspection>,
+    mut entity_orphaned_events: EventReader<NetworkEntityOrphanedEvent>,
+    mut spawn_request_events: EventReader<NetworkSpawnRequestEvent>,
+    mut spawn_response_events: EventReader<NetworkSpawnResponseEvent>,
+    mut ownership_request_events: EventReader<NetworkOwnershipRequestEvent>,
+    mut ownership_response_events: EventReader<NetworkOwnershipResponseEvent>,
+    mut entity_ref_events_on_client: EventReader<NetworkEvent<TestEntityRefEvent>>,
+    mut entity_ref_events_on_server: EventReader<NetworkServerEvent<TestEntityRefEvent>>,
+    mut entity_ref_entity_events: EventReader<NetworkEntityEvent<TestEntityRefEvent>>,
+    mut state_request_events: EventReader<NetworkStateRequestEvent<TestGameState>>,
+    mut entity_event_dropped_events: EventReader<NetworkEntityEventDroppedEvent>,
+) {
+    for event in entity_orphaned_events.iter() {
+        introspection.entity_orphaned_events.push(event.clone());
+    }
+    for event in spawn_request_events.iter() {
+        introspection.spawn_request_events.push(event.clone());
+    }
+    for event in spawn_response_events.iter() {
+        introspection.spawn_response_events.push(event.clone());
+    }
+    for event in ownership_request_events.iter() {
+        introspection.ownership_request_events.push(event.clone());
+    }
+    for event in ownership_response_events.iter() {
+        introspection.ownership_response_events.push(event.clone());
+    }
+    for event in entity_ref_events_on_client.iter() {
+        introspection
+            .entity_ref_events_on_client
+            .push(event.data.clone());
+    }
+    for event in entity_ref_events_on_server.iter() {
+        introspection
+            .entity_ref_events_on_server
+            .push(event.data.clone());
+    }
+    for event in entity_ref_entity_events.iter() {
+        introspection
+            .entity_ref_entity_events
+            .push((event.entity, event.data.clone()));
+    }
+    for event in state_request_events.iter() {
+        introspection.state_request_events.push(event.clone());
+    }
+    for event in entity_event_dropped_events.iter() {
+        introspection
+            .entity_event_dropped_events
+            .push(event.clone());
+    }
 }
@@ -1,4 +1,5 @@
 mod app_setup_for_tests;
+mod helpers;
 mod introspection;
 mod pseudo_network;
 mod test_environment;
@@ -7,8 +8,15 @@ mod test_structs;
 pub mod prelude {
     pub use super::{
         app_setup_for_tests::AppSetupForTests,
+        helpers::{
+            find_entities, find_entity, has_entity, pseudo_socket_pair, received_entity_events,
+            send_entity_event, setup_server_and_client, setup_server_and_two_clients,
+        },
         pseudo_network::{PseudoConnector, PseudoHost, PseudoNetwork},
         test_environment::TestEnvironment,
-        test_structs::{TestGameEvent, TestPlayerData},
+        test_structs::{
+            TestComponent, TestEntityRefEvent, TestGameEvent, TestGameState, TestInput,
+            TestLinkPrefab, TestPlayerData, TestPrefab, TestRollbackState, TestScore, TestSecret,
+        },
     };
 }
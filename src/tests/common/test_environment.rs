@@ -125,6 +125,10 @@ impl TestApp {
         network.client_mut().unwrap()
     }
 
+    pub fn clock(&self) -> &NetworkClock {
+        self.app.world.get_resource::<NetworkClock>().unwrap()
+    }
+
     pub fn introspect(&mut self) -> Mut<Introspection> {
         self.app.world.get_resource_mut::<Introspection>().unwrap()
     }
@@ -1,3 +1,5 @@
+use crate::prelude::*;
+use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -5,7 +7,59 @@ pub struct TestGameEvent {
     pub foo: String,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestEntityRefEvent {
+    #[serde(with = "network_entity")]
+    pub target: Entity,
+    #[serde(with = "network_entity_option")]
+    pub other: Option<Entity>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestScore {
+    pub value: u32,
+}
+
+// only sent to the player it belongs to
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestSecret {
+    pub player: NetworkPlayer,
+    pub value: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TestGameState {
+    Lobby,
+    InGame,
+    Results,
+}
+
 #[derive(Clone, Default, Serialize, Deserialize)]
 pub struct TestPlayerData {
     pub name: String,
 }
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestInput {
+    pub value: u32,
+}
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct TestComponent(pub u32);
+
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestPrefab {
+    pub value: u32,
+}
+
+// a prefab that refers to another entity
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestLinkPrefab {
+    #[serde(with = "network_entity_option")]
+    pub link: Option<Entity>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct TestRollbackState {
+    pub sum: u32,
+}
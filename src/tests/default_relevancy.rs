@@ -0,0 +1,122 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+#[test]
+fn owner_only() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    env["server"]
+        .world()
+        .insert_resource(NetworkDefaultRelevancy::OwnerOnly);
+    let unowned = NetworkEntity::new();
+    let owned = NetworkEntity::new();
+    env["server"].world().spawn().insert(unowned);
+    env["server"].world().spawn().insert(owned);
+    env["server"]
+        .server()
+        .set_entity_owner(owned, Some(client1_me));
+    env.flush_network();
+
+    assert!(!has_entity(&mut env, "client1", unowned));
+    assert!(!has_entity(&mut env, "client2", unowned));
+    assert!(has_entity(&mut env, "client1", owned));
+    assert!(!has_entity(&mut env, "client2", owned));
+    assert!(env["client2"].introspect().entity_spawn_events.is_empty());
+
+    env["server"]
+        .server()
+        .set_entity_relevant(owned, client2_me, true);
+    env.flush_network();
+    assert!(has_entity(&mut env, "client2", owned));
+}
+
+#[test]
+fn visibility_blocked() {
+    let mut env = TestEnvironment::default();
+    let (_, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(NetworkVisibility::default().block(client2_me));
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+    // never leaked, not even for a frame
+    assert!(env["client2"].introspect().entity_spawn_events.is_empty());
+}
+
+#[test]
+fn visibility_allowed() {
+    let mut env = TestEnvironment::default();
+    let (_, client2_me) = setup_server_and_two_clients(&mut env);
+
+    env["server"]
+        .world()
+        .insert_resource(NetworkDefaultRelevancy::OwnerOnly);
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(NetworkVisibility::default().allow(client2_me));
+    env.flush_network();
+
+    assert!(!has_entity(&mut env, "client1", network_entity));
+    assert!(has_entity(&mut env, "client2", network_entity));
+}
+
+#[test]
+fn manual_relevancy_wins() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity).insert(
+        NetworkVisibility::default()
+            .block(client1_me)
+            .allow(client2_me),
+    );
+    let server = env["server"].server();
+    server.set_entity_relevant(network_entity, client1_me, true);
+    server.set_entity_relevant(network_entity, client2_me, false);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+}
+
+fn remove_visibility(mut commands: Commands, query: Query<Entity, With<NetworkVisibility>>) {
+    for entity in query.iter() {
+        commands.entity(entity).remove::<NetworkVisibility>();
+    }
+}
+
+#[test]
+fn visibility_removed() {
+    let mut env = TestEnvironment::default();
+    let (_, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(NetworkVisibility::default().block(client2_me));
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client2", network_entity));
+
+    // removed after the network update, before the frame ends
+    env["server"]
+        .app()
+        .add_system_to_stage(CoreStage::PostUpdate, remove_visibility);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(has_entity(&mut env, "client2", network_entity));
+}
@@ -0,0 +1,100 @@
+use super::common::prelude::*;
+use crate::{messages::NetworkMessage, prelude::*, serialized_struct::NetworkSerializedStruct};
+
+// sends an entity event straight down the server's socket, whether the client has the entity
+// or not
+fn receive(env: &mut TestEnvironment, network_entity: NetworkEntity, foo: &str) {
+    let message = NetworkMessage::EntityEvent {
+        entity: network_entity,
+        from: None,
+        data: NetworkSerializedStruct::from_struct(&TestGameEvent { foo: foo.into() }),
+    }
+    .serialize();
+    env["server"].server().players[0].socket.send(message);
+}
+
+#[test]
+fn same_frame_as_spawn() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    for i in 0..20 {
+        let network_entity = NetworkEntity::new();
+        env["server"].world().spawn().insert(network_entity);
+        env["server"]
+            .server()
+            .send_to_entity(network_entity, TestGameEvent { foo: i.to_string() });
+    }
+    env.flush_network();
+
+    assert_eq!(received_entity_events(&mut env).len(), 20);
+    assert!(env["client"]
+        .introspect()
+        .entity_event_dropped_events
+        .is_empty());
+}
+
+#[test]
+fn buffered_until_spawned() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    receive(&mut env, network_entity, "first");
+    env["client"].app().update();
+    env["client"].app().update();
+    assert!(received_entity_events(&mut env).is_empty());
+
+    env["client"].world().spawn().insert(network_entity);
+    receive(&mut env, network_entity, "second");
+    env["client"].app().update();
+
+    assert_eq!(received_entity_events(&mut env), vec!["first", "second"]);
+    assert!(env["client"]
+        .introspect()
+        .entity_event_dropped_events
+        .is_empty());
+}
+
+#[test]
+fn dropped_after_buffer_ticks() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    env["client"].network().set_entity_event_buffer_ticks(2);
+    let network_entity = NetworkEntity::new();
+    receive(&mut env, network_entity, "lost");
+    for _ in 0..2 {
+        env["client"].app().update();
+        assert!(env["client"]
+            .introspect()
+            .entity_event_dropped_events
+            .is_empty());
+    }
+    env["client"].app().update();
+
+    assert!(received_entity_events(&mut env).is_empty());
+    let dropped = env["client"]
+        .introspect()
+        .entity_event_dropped_events
+        .clone();
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].network_entity, network_entity);
+    assert_eq!(dropped[0].from, None);
+    assert_eq!(dropped[0].type_name, NetworkTypeName::of::<TestGameEvent>());
+}
+
+#[test]
+fn no_buffer() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    env["client"].network().set_entity_event_buffer_ticks(0);
+    receive(&mut env, NetworkEntity::new(), "lost");
+    env["client"].app().update();
+
+    assert_eq!(
+        env["client"].introspect().entity_event_dropped_events.len(),
+        1
+    );
+}
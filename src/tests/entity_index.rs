@@ -0,0 +1,206 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn lookup(env: &mut TestEnvironment, name: &str, network_entity: NetworkEntity) -> Option<Entity> {
+    env[name]
+        .world()
+        .get_resource::<NetworkEntities>()
+        .unwrap()
+        .get(network_entity)
+}
+
+fn has_owner(env: &mut TestEnvironment, name: &str, network_entity: NetworkEntity) -> bool {
+    let entity = lookup(env, name, network_entity).unwrap();
+    env[name]
+        .world()
+        .get::<NetworkEntityOwner>(entity)
+        .is_some()
+}
+
+#[test]
+fn server_index() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+
+    assert_eq!(lookup(&mut env, "server", network_entity), Some(entity));
+    let entities = env["server"]
+        .world()
+        .get_resource::<NetworkEntities>()
+        .unwrap();
+    assert_eq!(entities.network_entity(entity), Some(network_entity));
+    assert_eq!(entities.len(), 1);
+    assert_eq!(
+        entities.iter().collect::<Vec<_>>(),
+        vec![(network_entity, entity)]
+    );
+
+    env["server"].world().despawn(entity);
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "server", network_entity), None);
+    assert!(env["server"]
+        .world()
+        .get_resource::<NetworkEntities>()
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn client_index() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+
+    let local_entity = env["client"].introspect().entity_spawn_events[0].entity;
+    assert_eq!(
+        lookup(&mut env, "client", network_entity),
+        Some(local_entity)
+    );
+
+    env["server"].world().despawn(entity);
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "client", network_entity), None);
+}
+
+#[test]
+fn remapped_client_spawn() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let client_me = env["client"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    let entity = env["client"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestPrefab { value: 4 })
+        .id();
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "client", network_entity), Some(entity));
+
+    let server_network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(server_network_entity);
+    env["server"]
+        .server()
+        .confirm_spawn_request(client_me, network_entity, server_network_entity);
+    env.flush_network();
+
+    assert_eq!(lookup(&mut env, "client", network_entity), None);
+    assert_eq!(
+        lookup(&mut env, "client", server_network_entity),
+        Some(entity)
+    );
+}
+
+#[test]
+fn component_removed() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "server", network_entity), Some(entity));
+
+    env["server"]
+        .world()
+        .entity_mut(entity)
+        .remove::<NetworkEntity>();
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "server", network_entity), None);
+}
+
+#[test]
+fn despawned_after_update() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    // removals made after the network update are only visible until the frame ends
+    env["server"].app().add_system_to_stage(
+        CoreStage::PostUpdate,
+        |mut commands: Commands, query: Query<Entity, With<NetworkEntity>>| {
+            for entity in query.iter() {
+                commands.entity(entity).despawn();
+            }
+        },
+    );
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "server", network_entity), None);
+}
+
+#[test]
+fn despawned_in_last() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    env["server"].app().add_system_to_stage(
+        CoreStage::Last,
+        |mut commands: Commands, query: Query<Entity, With<NetworkEntity>>| {
+            for entity in query.iter() {
+                commands.entity(entity).despawn();
+            }
+        },
+    );
+    env.flush_network();
+    assert_eq!(lookup(&mut env, "server", network_entity), None);
+}
+
+#[test]
+fn despawned_unseen() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+
+    // the removal is forgotten before the network or the cleanup stage could see it
+    env["server"].world().despawn(entity);
+    env["server"].world().clear_trackers();
+    env["server"].app().update();
+    assert_eq!(lookup(&mut env, "server", network_entity), None);
+}
+
+#[test]
+fn owner_after_request() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env["server"]
+        .server()
+        .set_entity_ownership_policy(network_entity, NetworkOwnershipPolicy::Approve);
+    env.flush_network();
+    assert!(has_owner(&mut env, "server", network_entity));
+    assert!(!has_owner(&mut env, "client", network_entity));
+
+    env["client"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    assert!(!has_owner(&mut env, "server", network_entity));
+    assert!(has_owner(&mut env, "client", network_entity));
+
+    env["client"]
+        .client()
+        .release_entity_ownership(network_entity);
+    env.flush_network();
+    assert!(has_owner(&mut env, "server", network_entity));
+    assert!(!has_owner(&mut env, "client", network_entity));
+}
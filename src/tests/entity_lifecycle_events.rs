@@ -0,0 +1,115 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+
+#[test]
+fn spawn_event() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    let entity = find_entity(env["client"].app(), network_entity).unwrap();
+    let introspect = env["client"].introspect();
+    assert_eq!(introspect.entity_spawn_events.len(), 1);
+    assert_eq!(introspect.entity_spawn_events[0].entity, entity);
+    assert_eq!(
+        introspect.entity_spawn_events[0].network_entity,
+        network_entity
+    );
+    assert!(env["server"].introspect().entity_spawn_events.is_empty());
+}
+
+#[test]
+fn despawn_event_destroyed() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    let server_entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+    let entity = find_entity(env["client"].app(), network_entity).unwrap();
+    env["server"].world().despawn(server_entity);
+    env.flush_network();
+
+    let introspect = env["client"].introspect();
+    assert_eq!(introspect.entity_despawn_events.len(), 1);
+    assert_eq!(introspect.entity_despawn_events[0].entity, entity);
+    assert_eq!(
+        introspect.entity_despawn_events[0].network_entity,
+        network_entity
+    );
+    assert_eq!(
+        introspect.entity_despawn_events[0].reason,
+        NetworkEntityDespawnReason::Destroyed
+    );
+}
+
+#[test]
+fn despawn_event_irrelevant() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client_me, false);
+    env.flush_network();
+
+    let introspect = env["client"].introspect();
+    assert_eq!(introspect.entity_despawn_events.len(), 1);
+    assert_eq!(
+        introspect.entity_despawn_events[0].reason,
+        NetworkEntityDespawnReason::Irrelevant
+    );
+}
+
+#[test]
+fn delay_despawn() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    let server_entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+    let entity = find_entity(env["client"].app(), network_entity).unwrap();
+    env["client"]
+        .world()
+        .entity_mut(entity)
+        .insert(NetworkDelayDespawn);
+    env["server"].world().despawn(server_entity);
+    env.flush_network();
+
+    assert!(env["client"].world().get_entity(entity).is_some());
+    assert_eq!(find_entity(env["client"].app(), network_entity), None);
+    let despawned = env["client"]
+        .world()
+        .get::<NetworkDespawned>(entity)
+        .unwrap()
+        .clone();
+    assert_eq!(despawned.network_entity, network_entity);
+    assert_eq!(despawned.reason, NetworkEntityDespawnReason::Destroyed);
+
+    env["client"]
+        .world()
+        .entity_mut(entity)
+        .remove::<NetworkDelayDespawn>();
+    env.flush_network();
+    assert!(env["client"].world().get_entity(entity).is_none());
+}
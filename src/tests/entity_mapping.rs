@@ -0,0 +1,239 @@
+use super::common::prelude::*;
+use crate::{entity_map::UNMAPPED_ENTITY, prelude::*, serializer::try_deserialize};
+
+#[test]
+fn server_to_client() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    // spawn a few entities on the client first, so local entity ids don't line up
+    for _ in 0..3 {
+        env["client"].world().spawn();
+    }
+    let target = NetworkEntity::new();
+    let other = NetworkEntity::new();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    let server_other = env["server"].world().spawn().insert(other).id();
+    env.flush_network();
+
+    env["server"].server().send_to_all(TestEntityRefEvent {
+        target: server_target,
+        other: Some(server_other),
+    });
+    env.flush_network();
+
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    let client_other = find_entity(env["client"].app(), other).unwrap();
+    assert_ne!(client_target, server_target);
+    assert_eq!(
+        env["client"].introspect().entity_ref_events_on_client,
+        vec![TestEntityRefEvent {
+            target: client_target,
+            other: Some(client_other),
+        }]
+    );
+}
+
+#[test]
+fn client_to_server() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    for _ in 0..3 {
+        env["client"].world().spawn();
+    }
+    let target = NetworkEntity::new();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    env.flush_network();
+
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    env["client"].client().send(TestEntityRefEvent {
+        target: client_target,
+        other: None,
+    });
+    env.flush_network();
+
+    assert_eq!(
+        env["server"].introspect().entity_ref_events_on_server,
+        vec![TestEntityRefEvent {
+            target: server_target,
+            other: None,
+        }]
+    );
+}
+
+#[test]
+fn entity_event_from_owner() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    for _ in 0..3 {
+        env["client"].world().spawn();
+    }
+    let sender = NetworkEntity::new();
+    let target = NetworkEntity::new();
+    let server_sender = env["server"].world().spawn().insert(sender).id();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    env.flush_network();
+
+    env["server"]
+        .world()
+        .get_mut::<NetworkEntityOwner>(server_sender)
+        .unwrap()
+        .send(TestEntityRefEvent {
+            target: server_target,
+            other: None,
+        });
+    env.flush_network();
+
+    let client_sender = find_entity(env["client"].app(), sender).unwrap();
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    assert_eq!(
+        env["client"].introspect().entity_ref_entity_events,
+        vec![(
+            client_sender,
+            TestEntityRefEvent {
+                target: client_target,
+                other: None,
+            }
+        )]
+    );
+}
+
+#[test]
+fn unmapped_entities() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let target = NetworkEntity::new();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    env.flush_network();
+
+    let local_only = env["server"].world().spawn().id();
+    env["server"].server().send_to_all(TestEntityRefEvent {
+        target: server_target,
+        other: Some(local_only),
+    });
+    env.flush_network();
+
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    assert_eq!(
+        env["client"].introspect().entity_ref_events_on_client,
+        vec![TestEntityRefEvent {
+            target: client_target,
+            other: None,
+        }]
+    );
+}
+
+#[test]
+fn unmapped_entity_drops_event() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let local_only = env["server"].world().spawn().id();
+    env["server"].server().send_to_all(TestEntityRefEvent {
+        target: local_only,
+        other: None,
+    });
+    env["server"]
+        .server()
+        .send_to_all(TestGameEvent { foo: "bar".into() });
+    env.flush_network();
+
+    let introspect = env["client"].introspect();
+    assert!(introspect.entity_ref_events_on_client.is_empty());
+    assert_eq!(introspect.test_game_events_on_client.len(), 1);
+}
+
+#[test]
+fn entity_spawned_in_same_update() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    // the event is sent before the network has seen the entity
+    let target = NetworkEntity::new();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    env["server"].server().send_to_all(TestEntityRefEvent {
+        target: server_target,
+        other: None,
+    });
+    env.flush_network();
+
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    assert_eq!(
+        env["client"].introspect().entity_ref_events_on_client,
+        vec![TestEntityRefEvent {
+            target: client_target,
+            other: None,
+        }]
+    );
+}
+
+#[test]
+fn prefab_payload() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    for _ in 0..3 {
+        env["client"].world().spawn();
+    }
+    let target = NetworkEntity::new();
+    let server_target = env["server"].world().spawn().insert(target).id();
+    env.flush_network();
+
+    let linked = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(linked)
+        .insert(TestLinkPrefab {
+            link: Some(server_target),
+        });
+    env.flush_network();
+
+    let client_target = find_entity(env["client"].app(), target).unwrap();
+    let client_linked = find_entity(env["client"].app(), linked).unwrap();
+    assert_ne!(client_target, server_target);
+    assert_eq!(
+        env["client"].world().get::<TestLinkPrefab>(client_linked),
+        Some(&TestLinkPrefab {
+            link: Some(client_target),
+        })
+    );
+}
+
+#[test]
+fn unmapped_entity_error() {
+    let data = "(target:None,other:None)";
+
+    assert_eq!(
+        try_deserialize::<TestEntityRefEvent>(data).err(),
+        Some(UNMAPPED_ENTITY.to_string())
+    );
+    assert!(matches!(
+        try_deserialize::<TestEntityRefEvent>("(target:"),
+        Err(error) if error != UNMAPPED_ENTITY
+    ));
+}
@@ -1,5 +1,4 @@
 // TODO: fail to set owner if player doesn't exist?
-// TODO: reset owner on player disconnect
 
 use super::common::prelude::*;
 use crate::prelude::*;
@@ -0,0 +1,105 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn setup(env: &mut TestEnvironment, policy: NetworkOwnerLeavePolicy) -> (NetworkEntity, Entity) {
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    let server = env["server"].server();
+    server.set_entity_owner(network_entity, Some(client1_me));
+    server.set_entity_owner_leave_policy(network_entity, policy);
+    env.flush_network();
+    env["client1"].network().stop();
+    env.flush_network();
+    (network_entity, entity)
+}
+
+#[test]
+fn revert_to_server() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env, NetworkOwnerLeavePolicy::RevertToServer);
+
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+    assert!(env["server"].world().get_entity(entity).is_some());
+    let introspect = env["server"].introspect();
+    assert_eq!(introspect.entity_orphaned_events.len(), 1);
+    assert_eq!(introspect.entity_orphaned_events[0].entity, entity);
+    assert_eq!(
+        introspect.entity_orphaned_events[0].policy,
+        NetworkOwnerLeavePolicy::RevertToServer
+    );
+    assert!(env["client2"]
+        .introspect()
+        .entity_orphaned_events
+        .is_empty());
+}
+
+#[test]
+fn despawn() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, entity) = setup(&mut env, NetworkOwnerLeavePolicy::Despawn);
+
+    assert!(env["server"].world().get_entity(entity).is_none());
+    assert_eq!(
+        env["server"].introspect().entity_orphaned_events[0].network_entity,
+        network_entity
+    );
+    let mut query = env["client2"].world().query::<&NetworkEntity>();
+    assert_eq!(query.iter(env["client2"].world()).count(), 0);
+}
+
+#[test]
+fn reserve_and_reclaim() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, _) = setup(&mut env, NetworkOwnerLeavePolicy::Reserve { seconds: 60. });
+    let old_client1_me = env["server"].introspect().entity_orphaned_events[0].owner;
+
+    env["client2"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    assert!(!env["client2"].introspect().ownership_response_events[0].approved);
+
+    env.start_client("client1", "server");
+    env.flush_network();
+    let client1_me = env["client1"].network().me().unwrap();
+    assert_eq!(
+        env["server"]
+            .server()
+            .reclaim_reserved_entities(old_client1_me, client1_me),
+        vec![network_entity]
+    );
+    env.flush_network();
+
+    assert_eq!(
+        env["client1"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+    assert!(env["server"]
+        .server()
+        .reclaim_reserved_entities(old_client1_me, client1_me)
+        .is_empty());
+}
+
+#[test]
+fn reservation_expires() {
+    let mut env = TestEnvironment::default();
+    let (network_entity, _) = setup(&mut env, NetworkOwnerLeavePolicy::Reserve { seconds: 0. });
+
+    env["client2"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+
+    assert!(env["server"]
+        .introspect()
+        .ownership_request_events
+        .iter()
+        .any(|e| e.network_entity == network_entity));
+}
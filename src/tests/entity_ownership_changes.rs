@@ -0,0 +1,136 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+
+fn ownership_changes(
+    env: &mut TestEnvironment,
+    app: &str,
+) -> Vec<(NetworkEntity, Option<NetworkPlayer>, Option<NetworkPlayer>)> {
+    env[app]
+        .introspect()
+        .entity_ownership_changed_events
+        .iter()
+        .map(|e| (e.network_entity, e.old_owner, e.new_owner))
+        .collect()
+}
+
+#[test]
+fn owner_visible_to_all_clients() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client1_me));
+    env.flush_network();
+
+    for app in ["server", "client1", "client2"] {
+        assert_eq!(
+            env[app].network().entity_owner(network_entity),
+            Some(client1_me)
+        );
+        assert_eq!(
+            ownership_changes(&mut env, app),
+            vec![(network_entity, None, Some(client1_me))]
+        );
+    }
+}
+
+#[test]
+fn owner_transfer() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let client2_me = env["client2"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client1_me));
+    env.flush_network();
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client2_me));
+    env.flush_network();
+
+    for app in ["server", "client1", "client2"] {
+        assert_eq!(
+            env[app].network().entity_owner(network_entity),
+            Some(client2_me)
+        );
+        assert_eq!(
+            ownership_changes(&mut env, app),
+            vec![
+                (network_entity, None, Some(client1_me)),
+                (network_entity, Some(client1_me), Some(client2_me)),
+            ]
+        );
+    }
+}
+
+#[test]
+fn owner_sent_on_spawn() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client1_me));
+    env.flush_network();
+
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    assert_eq!(
+        env["client2"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+    assert!(ownership_changes(&mut env, "client2").is_empty());
+}
+
+#[test]
+fn owner_reset_on_disconnect() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client1_me));
+    env.flush_network();
+    env["client1"].network().stop();
+    env.flush_network();
+
+    for app in ["server", "client2"] {
+        assert_eq!(env[app].network().entity_owner(network_entity), None);
+        assert_eq!(
+            ownership_changes(&mut env, app).last(),
+            Some(&(network_entity, Some(client1_me), None))
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+
+fn responses(env: &mut TestEnvironment, app: &str) -> Vec<(NetworkEntity, bool, bool)> {
+    env[app]
+        .introspect()
+        .ownership_response_events
+        .iter()
+        .map(|e| (e.network_entity, e.release, e.approved))
+        .collect()
+}
+
+fn setup(env: &mut TestEnvironment) -> NetworkEntity {
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    network_entity
+}
+
+#[test]
+fn manual_approve() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+
+    let requests: Vec<(NetworkPlayer, NetworkEntity)> = env["server"]
+        .introspect()
+        .ownership_request_events
+        .iter()
+        .map(|e| (e.player, e.network_entity))
+        .collect();
+    assert_eq!(requests, vec![(client1_me, network_entity)]);
+    assert!(responses(&mut env, "client1").is_empty());
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+
+    env["server"]
+        .server()
+        .approve_ownership_request(client1_me, network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        responses(&mut env, "client1"),
+        vec![(network_entity, false, true)]
+    );
+    assert_eq!(
+        env["client2"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+}
+
+#[test]
+fn manual_deny() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .deny_ownership_request(client1_me, network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        responses(&mut env, "client1"),
+        vec![(network_entity, false, false)]
+    );
+    assert_eq!(env["client1"].network().entity_owner(network_entity), None);
+}
+
+#[test]
+fn approve_without_request() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .approve_ownership_request(client1_me, network_entity);
+    env.flush_network();
+
+    assert!(responses(&mut env, "client1").is_empty());
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+}
+
+#[test]
+fn policy_approve_if_unowned() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_ownership_policy(network_entity, NetworkOwnershipPolicy::ApproveIfUnowned);
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    env["client2"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+
+    assert!(env["server"]
+        .introspect()
+        .ownership_request_events
+        .is_empty());
+    assert_eq!(
+        responses(&mut env, "client1"),
+        vec![(network_entity, false, true)]
+    );
+    assert_eq!(
+        responses(&mut env, "client2"),
+        vec![(network_entity, false, false)]
+    );
+    assert_eq!(
+        env["server"].network().entity_owner(network_entity),
+        Some(client1_me)
+    );
+}
+
+#[test]
+fn policy_deny() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    env["server"]
+        .server()
+        .set_entity_ownership_policy(network_entity, NetworkOwnershipPolicy::Deny);
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        responses(&mut env, "client1"),
+        vec![(network_entity, false, false)]
+    );
+}
+
+#[test]
+fn release() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    env["server"]
+        .server()
+        .set_entity_ownership_policy(network_entity, NetworkOwnershipPolicy::Approve);
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    env["client2"]
+        .client()
+        .release_entity_ownership(network_entity);
+    env.flush_network();
+    env["client1"]
+        .client()
+        .release_entity_ownership(network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        responses(&mut env, "client2"),
+        vec![(network_entity, true, false)]
+    );
+    assert_eq!(
+        responses(&mut env, "client1"),
+        vec![(network_entity, false, true), (network_entity, true, true)]
+    );
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+    assert_eq!(env["client2"].network().entity_owner(network_entity), None);
+}
+
+#[test]
+fn despawn_removes_pending_request() {
+    let mut env = TestEnvironment::default();
+    setup(&mut env);
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"].world().spawn().insert(network_entity).id();
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    env["server"].world().despawn(entity);
+    env.flush_network();
+
+    // the request doesn't carry over to an entity spawned with the same handle
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env["server"]
+        .server()
+        .approve_ownership_request(client1_me, network_entity);
+    env.flush_network();
+
+    assert!(responses(&mut env, "client1").is_empty());
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+}
+
+#[test]
+fn irrelevant_entity_denied() {
+    let mut env = TestEnvironment::default();
+    let network_entity = setup(&mut env);
+
+    let client1_me = env["client1"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client1_me, false);
+    env.flush_network();
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    assert!(env["server"]
+        .introspect()
+        .ownership_request_events
+        .is_empty());
+
+    env["server"]
+        .server()
+        .set_entity_ownership_policy(network_entity, NetworkOwnershipPolicy::Approve);
+    env["client1"]
+        .client()
+        .request_entity_ownership(network_entity);
+    env.flush_network();
+    assert_eq!(env["server"].network().entity_owner(network_entity), None);
+}
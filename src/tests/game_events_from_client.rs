@@ -93,3 +93,29 @@ fn client_send() {
         "bar"
     );
 }
+
+#[test]
+fn client_send_many() {
+    // Test that the server receives every event a client sent in one update, not one per update
+
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+    for foo in ["a", "b", "c"] {
+        env["client"]
+            .client()
+            .send(TestGameEvent { foo: foo.into() });
+    }
+    env["client"].app().update();
+    env["server"].app().update();
+
+    let received: Vec<String> = env["server"]
+        .introspect()
+        .test_game_events_on_server
+        .iter()
+        .map(|event| event.data.foo.clone())
+        .collect();
+    assert_eq!(received, vec!["a", "b", "c"]);
+}
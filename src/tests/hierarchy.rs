@@ -0,0 +1,190 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn local_parent(app: &mut App, network_entity: NetworkEntity) -> Option<Entity> {
+    let entity = find_entity(app, network_entity).unwrap();
+    app.world.get::<Parent>(entity).map(|parent| parent.0)
+}
+
+fn spawn_hierarchy(env: &mut TestEnvironment) -> (NetworkEntity, NetworkEntity, Entity, Entity) {
+    let parent = NetworkEntity::new();
+    let child = NetworkEntity::new();
+    let world = env["server"].world();
+    let child_entity = world.spawn().insert(child).id();
+    let parent_entity = world
+        .spawn()
+        .insert(parent)
+        .push_children(&[child_entity])
+        .id();
+    (parent, child, parent_entity, child_entity)
+}
+
+#[test]
+fn parent_replicated() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let (parent, child, _, _) = spawn_hierarchy(&mut env);
+    env.flush_network();
+
+    let parent_entity = find_entity(env["client"].app(), parent).unwrap();
+    assert_eq!(
+        local_parent(env["client"].app(), child),
+        Some(parent_entity)
+    );
+    assert_eq!(local_parent(env["client"].app(), parent), None);
+}
+
+#[test]
+fn parents_spawn_first() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let mut chain = vec![];
+    let mut last_entity = None;
+    for _ in 0..5 {
+        let network_entity = NetworkEntity::new();
+        let entity = env["server"].world().spawn().insert(network_entity).id();
+        if let Some(last_entity) = last_entity {
+            env["server"]
+                .world()
+                .entity_mut(entity)
+                .push_children(&[last_entity]);
+        }
+        chain.insert(0, network_entity);
+        last_entity = Some(entity);
+    }
+    env.flush_network();
+
+    let spawned: Vec<NetworkEntity> = env["client"]
+        .introspect()
+        .entity_spawn_events
+        .iter()
+        .map(|event| event.network_entity)
+        .collect();
+    assert_eq!(spawned, chain);
+    for pair in chain.windows(2) {
+        let parent_entity = find_entity(env["client"].app(), pair[0]).unwrap();
+        assert_eq!(
+            local_parent(env["client"].app(), pair[1]),
+            Some(parent_entity)
+        );
+    }
+}
+
+#[test]
+fn parent_changes() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let (_, child, parent_entity, child_entity) = spawn_hierarchy(&mut env);
+    let other = NetworkEntity::new();
+    let other_entity = env["server"].world().spawn().insert(other).id();
+    env.flush_network();
+
+    env["server"]
+        .world()
+        .entity_mut(parent_entity)
+        .remove_children(&[child_entity]);
+    env["server"]
+        .world()
+        .entity_mut(other_entity)
+        .push_children(&[child_entity]);
+    env.flush_network();
+    let other_on_client = find_entity(env["client"].app(), other).unwrap();
+    assert_eq!(
+        local_parent(env["client"].app(), child),
+        Some(other_on_client)
+    );
+
+    env["server"]
+        .world()
+        .entity_mut(other_entity)
+        .remove_children(&[child_entity]);
+    env.flush_network();
+    assert_eq!(local_parent(env["client"].app(), child), None);
+}
+
+#[test]
+fn relevancy_follows_parent() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let (parent, child, _, _) = spawn_hierarchy(&mut env);
+    env.flush_network();
+    assert!(find_entity(env["client"].app(), child).is_some());
+
+    let client_me = env["client"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_relevant(parent, client_me, false);
+    env.flush_network();
+    assert!(find_entity(env["client"].app(), parent).is_none());
+    assert!(find_entity(env["client"].app(), child).is_none());
+
+    env["server"]
+        .server()
+        .set_entity_relevant(parent, client_me, true);
+    env.flush_network();
+    let parent_entity = find_entity(env["client"].app(), parent).unwrap();
+    assert_eq!(
+        local_parent(env["client"].app(), child),
+        Some(parent_entity)
+    );
+}
+
+#[test]
+fn relevancy_follows_parent_and_own() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let (parent, child, _, _) = spawn_hierarchy(&mut env);
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_relevant(child, client_me, false);
+    env.flush_network();
+    assert!(find_entity(env["client"].app(), parent).is_some());
+    assert!(find_entity(env["client"].app(), child).is_none());
+}
+
+#[test]
+fn relevancy_independent_of_parent() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let (parent, child, _, _) = spawn_hierarchy(&mut env);
+    env["server"]
+        .server()
+        .set_entity_follow_parent_relevancy(child, false);
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_relevant(parent, client_me, false);
+    env.flush_network();
+    assert!(find_entity(env["client"].app(), parent).is_none());
+    assert!(find_entity(env["client"].app(), child).is_some());
+}
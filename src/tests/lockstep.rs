@@ -0,0 +1,236 @@
+use super::common::prelude::*;
+use crate::{
+    lockstep::{NetworkLockstepChecksums, NetworkLockstepServer, LOCKSTEP_WINDOW},
+    messages::NetworkMessage,
+    network_type_name::NetworkTypeName,
+    prelude::*,
+    serialized_struct::NetworkSerializedStruct,
+};
+
+fn confirmed_ticks(env: &mut TestEnvironment, app: &str) -> Vec<u64> {
+    env[app]
+        .introspect()
+        .lockstep_tick_events
+        .iter()
+        .map(|e| e.tick)
+        .collect()
+}
+
+#[test]
+fn lockstep_waits_for_all_players() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    assert_eq!(
+        env["client1"]
+            .client()
+            .send_lockstep_input(TestInput { value: 1 }),
+        2
+    );
+    env.flush_network();
+
+    assert!(confirmed_ticks(&mut env, "client1").is_empty());
+    assert!(confirmed_ticks(&mut env, "client2").is_empty());
+
+    assert_eq!(
+        env["client2"]
+            .client()
+            .send_lockstep_input(TestInput { value: 2 }),
+        2
+    );
+    env.flush_network();
+
+    assert_eq!(confirmed_ticks(&mut env, "client1"), vec![0, 1, 2]);
+    assert_eq!(confirmed_ticks(&mut env, "client2"), vec![0, 1, 2]);
+    let client1_me = env["client1"].network().me().unwrap();
+    let client2_me = env["client2"].network().me().unwrap();
+    for app in ["client1", "client2"] {
+        let introspect = env[app].introspect();
+        let inputs = &introspect.lockstep_tick_events[2].inputs;
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs.contains(&(client1_me, TestInput { value: 1 })));
+        assert!(inputs.contains(&(client2_me, TestInput { value: 2 })));
+        let inputs = &introspect.lockstep_tick_events[0].inputs;
+        assert!(inputs.contains(&(client1_me, TestInput::default())));
+    }
+}
+
+#[test]
+fn lockstep_input_delay() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server_client("server");
+    env.flush_network();
+
+    env["server"].client().set_lockstep_input_delay(5);
+    assert_eq!(
+        env["server"]
+            .client()
+            .send_lockstep_input(TestInput { value: 1 }),
+        5
+    );
+    assert_eq!(
+        env["server"]
+            .client()
+            .send_lockstep_input(TestInput { value: 2 }),
+        6
+    );
+    env.flush_network();
+
+    assert_eq!(
+        confirmed_ticks(&mut env, "server"),
+        vec![0, 1, 2, 3, 4, 5, 6]
+    );
+}
+
+#[test]
+fn lockstep_late_join() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.flush_network();
+
+    env["client1"].client().set_lockstep_input_delay(0);
+    for i in 0..5 {
+        env["client1"]
+            .client()
+            .send_lockstep_input(TestInput { value: i });
+    }
+    env.flush_network();
+    assert_eq!(confirmed_ticks(&mut env, "client1"), vec![0, 1, 2, 3, 4]);
+
+    env.create_client("client2", "server");
+    env.flush_network();
+    env["client1"]
+        .client()
+        .send_lockstep_input(TestInput { value: 5 });
+    env.flush_network();
+    assert_eq!(confirmed_ticks(&mut env, "client1").len(), 5);
+
+    env["client2"].client().set_lockstep_input_delay(0);
+    assert_eq!(
+        env["client2"]
+            .client()
+            .send_lockstep_input(TestInput { value: 5 }),
+        5
+    );
+    env.flush_network();
+    assert_eq!(confirmed_ticks(&mut env, "client1").len(), 6);
+    assert_eq!(confirmed_ticks(&mut env, "client2"), vec![5]);
+}
+
+#[test]
+fn lockstep_player_leave() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    env["client1"].client().set_lockstep_input_delay(0);
+    env["client1"]
+        .client()
+        .send_lockstep_input(TestInput { value: 1 });
+    env.flush_network();
+    assert!(confirmed_ticks(&mut env, "client1").is_empty());
+
+    env["client2"].network().stop();
+    env.flush_network();
+    assert_eq!(confirmed_ticks(&mut env, "client1"), vec![0]);
+}
+
+#[test]
+fn lockstep_desync() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    for app in ["client1", "client2"] {
+        env[app].client().set_lockstep_input_delay(0);
+        env[app]
+            .client()
+            .send_lockstep_input(TestInput { value: 1 });
+        env[app]
+            .client()
+            .send_lockstep_input(TestInput { value: 1 });
+    }
+    env.flush_network();
+
+    env["client1"].client().send_lockstep_checksum(0, 100);
+    env["client2"].client().send_lockstep_checksum(0, 100);
+    env["client1"].client().send_lockstep_checksum(1, 100);
+    env.flush_network();
+    assert!(env["server"].introspect().desync_events.is_empty());
+    assert!(env["client1"].introspect().desync_events.is_empty());
+
+    env["client2"].client().send_lockstep_checksum(1, 200);
+    env.flush_network();
+    for app in ["server", "client1", "client2"] {
+        let introspect = env[app].introspect();
+        assert_eq!(introspect.desync_events.len(), 1);
+        assert_eq!(introspect.desync_events[0].tick, 1);
+        assert_eq!(introspect.desync_events[0].checksums.len(), 2);
+    }
+}
+
+#[test]
+fn lockstep_ignores_inputs_outside_window() {
+    let mut lockstep = NetworkLockstepServer::default();
+    let player = NetworkPlayer::new();
+    let input = NetworkSerializedStruct::from_struct(&TestInput { value: 1 });
+    lockstep.add_player(player);
+
+    lockstep.receive(player, LOCKSTEP_WINDOW, input.clone());
+    for tick in 0..LOCKSTEP_WINDOW {
+        lockstep.receive(player, tick, input.clone());
+    }
+    for _ in 0..LOCKSTEP_WINDOW {
+        assert!(lockstep.advance().is_some());
+    }
+    assert!(lockstep.advance().is_none());
+}
+
+#[test]
+fn lockstep_drops_checksums_outside_window() {
+    let mut checksums = NetworkLockstepChecksums::default();
+    let player1 = NetworkPlayer::new();
+    let player2 = NetworkPlayer::new();
+    let players = |_| vec![player1, player2];
+
+    checksums.receive(player1, 0, 100, 0);
+    assert!(checksums.compare(players, 0).is_empty());
+    assert!(checksums.compare(players, LOCKSTEP_WINDOW).is_empty());
+
+    // tick 0 was dropped, so player2's late checksum can't complete it
+    checksums.receive(player2, 0, 200, LOCKSTEP_WINDOW);
+    assert!(checksums.compare(players, LOCKSTEP_WINDOW).is_empty());
+
+    checksums.receive(player1, 2 * LOCKSTEP_WINDOW, 100, 0);
+    checksums.receive(player2, 2 * LOCKSTEP_WINDOW, 200, 0);
+    assert!(checksums.compare(players, 0).is_empty());
+}
+
+#[test]
+fn lockstep_ignores_unregistered_types() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let message = NetworkMessage::LockstepInput {
+        tick: 2,
+        data: NetworkSerializedStruct::from_struct(&TestGameEvent { foo: "foo".into() }),
+    };
+    env["client"].client().socket.send(message.serialize());
+    env.flush_network();
+
+    let lockstep = &env["server"].server().lockstep;
+    assert!(!lockstep.contains_key(&NetworkTypeName::of::<TestGameEvent>()));
+}
@@ -1,18 +1,43 @@
+mod bandwidth;
+mod client_spawned_entities;
+mod clock_sync;
 mod common;
 mod connection_events;
+mod default_relevancy;
 mod entities_spawn_despawn;
+mod entity_event_buffering;
 mod entity_events_from_client;
 mod entity_events_from_owner;
 mod entity_events_from_server;
+mod entity_index;
+mod entity_lifecycle_events;
+mod entity_mapping;
 mod entity_owner;
+mod entity_owner_leave;
+mod entity_ownership_changes;
+mod entity_ownership_requests;
 mod entity_relevancy;
 mod game_events_from_client;
 mod game_events_from_server;
+mod hierarchy;
 mod is;
+mod lag_compensation;
+mod lockstep;
 mod multiple_joins_leaves;
+mod network_input;
 mod player_data;
 mod player_join_events;
 mod player_leave_events;
 mod players;
+mod prefabs;
+mod relevancy_cleanup;
+mod relevancy_groups;
+mod relevancy_rules;
+mod resources;
+mod rollback;
+mod shared_messages;
+mod spatial_interest;
+mod states;
+mod update_rate;
 
 // TODO: tests guaranteeing message order?
@@ -0,0 +1,205 @@
+use super::common::prelude::*;
+use crate::{
+    input::{NetworkInputBuffer, NetworkInputHistory, INPUT_REDUNDANCY, INPUT_WINDOW},
+    messages::NetworkMessage,
+    network_type_name::NetworkTypeName,
+    prelude::*,
+    serialized_struct::NetworkSerializedStruct,
+};
+
+fn test_input(value: u32) -> NetworkSerializedStruct {
+    NetworkSerializedStruct::from_struct(&TestInput { value })
+}
+
+fn server_input(env: &mut TestEnvironment, player: NetworkPlayer) -> Option<(TestInput, bool)> {
+    let inputs = env["server"]
+        .world()
+        .get_resource::<NetworkInputs<TestInput>>()
+        .unwrap();
+    inputs
+        .get_input(player)
+        .map(|input| (input.data.clone(), input.repeated))
+}
+
+#[test]
+fn input_from_client() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    assert!(server_input(&mut env, client_me).is_none());
+    env["client"].client().send_input(TestInput { value: 1 });
+    env.flush_network();
+
+    assert_eq!(
+        server_input(&mut env, client_me).unwrap().0,
+        TestInput { value: 1 }
+    );
+}
+
+#[test]
+fn input_before_clock_sync() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.flush_network();
+    env.create_client("client", "server");
+    while env["client"].network().client().is_none() {
+        env["client"].app().update();
+    }
+    assert!(!env["client"].client().clock.synchronized());
+
+    let client_me = env["client"].network().me().unwrap();
+    let server_tick = env["server"].server().tick();
+    env["client"].client().send_input(TestInput { value: 1 });
+    for _ in 0..10 {
+        env.flush_network();
+        if server_input(&mut env, client_me).is_some() {
+            break;
+        }
+    }
+
+    assert_eq!(
+        server_input(&mut env, client_me).unwrap().0,
+        TestInput { value: 1 }
+    );
+    // the held input is stamped with the estimated tick, not the tick an unsynced clock reports
+    let sent = env["client"]
+        .client()
+        .inputs
+        .get_mut(&NetworkTypeName::of::<TestInput>())
+        .unwrap()
+        .push(0, test_input(2));
+    assert!(sent[0].0 >= server_tick);
+}
+
+#[test]
+fn input_from_local_player() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server_client("server");
+    env.flush_network();
+
+    let server_me = env["server"].network().me().unwrap();
+    env["server"].client().send_input(TestInput { value: 1 });
+    env.flush_network();
+
+    assert_eq!(
+        server_input(&mut env, server_me).unwrap().0,
+        TestInput { value: 1 }
+    );
+}
+
+#[test]
+fn input_repeats_last() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    env["client"].client().send_input(TestInput { value: 1 });
+    env.flush_network();
+    env["client"].client().send_input(TestInput { value: 2 });
+    // the input is scheduled for an estimated tick, wait until the server reaches it
+    for _ in 0..10 {
+        env.flush_network();
+        if server_input(&mut env, client_me).unwrap().0 == (TestInput { value: 2 }) {
+            break;
+        }
+    }
+    env.flush_network();
+
+    assert_eq!(
+        server_input(&mut env, client_me).unwrap(),
+        (TestInput { value: 2 }, true)
+    );
+}
+
+#[test]
+fn input_removed_on_leave() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    env["client"].client().send_input(TestInput { value: 1 });
+    env.flush_network();
+    env["client"].network().stop();
+    env.flush_network();
+
+    assert!(server_input(&mut env, client_me).is_none());
+}
+
+#[test]
+fn input_history_redundancy() {
+    let mut history = NetworkInputHistory::default();
+    for i in 0..5 {
+        let inputs = history.push(10, test_input(i));
+        assert_eq!(inputs.len(), INPUT_REDUNDANCY.min(i as usize + 1));
+        assert_eq!(inputs.last().unwrap().0, 10 + i as u64);
+    }
+}
+
+#[test]
+fn input_buffer_jitter() {
+    let mut buffer = NetworkInputBuffer::default();
+    assert!(buffer.consume(1).is_none());
+
+    // inputs arrive out of order and with duplicates from redundancy
+    buffer.receive(3, test_input(3), 1);
+    buffer.receive(2, test_input(2), 1);
+    buffer.receive(2, test_input(2), 1);
+
+    let (input, repeated) = buffer.consume(2).unwrap();
+    assert_eq!(input.to_struct::<TestInput>().unwrap().value, 2);
+    assert!(!repeated);
+    let (input, repeated) = buffer.consume(3).unwrap();
+    assert_eq!(input.to_struct::<TestInput>().unwrap().value, 3);
+    assert!(!repeated);
+    let (input, repeated) = buffer.consume(4).unwrap();
+    assert_eq!(input.to_struct::<TestInput>().unwrap().value, 3);
+    assert!(repeated);
+
+    // late inputs are ignored once a newer tick has been consumed
+    buffer.receive(2, test_input(2), 4);
+    let (input, _) = buffer.consume(5).unwrap();
+    assert_eq!(input.to_struct::<TestInput>().unwrap().value, 3);
+}
+
+#[test]
+fn input_buffer_window() {
+    let mut buffer = NetworkInputBuffer::default();
+    let current_tick = INPUT_WINDOW * 2;
+
+    buffer.receive(current_tick + INPUT_WINDOW, test_input(1), current_tick);
+    buffer.receive(current_tick - INPUT_WINDOW, test_input(2), current_tick);
+    assert!(buffer.consume(u64::MAX).is_none());
+
+    buffer.receive(current_tick + INPUT_WINDOW - 1, test_input(3), current_tick);
+    let (input, _) = buffer.consume(u64::MAX).unwrap();
+    assert_eq!(input.to_struct::<TestInput>().unwrap().value, 3);
+}
+
+#[test]
+fn unregistered_input_dropped() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    let data = NetworkSerializedStruct::from_struct(&TestGameEvent { foo: "foo".into() });
+    let tick = env["server"].server().tick();
+    let message = NetworkMessage::Input {
+        inputs: vec![(tick + 1, data)],
+    };
+    env["client"].client().socket.send(message.serialize());
+    env.flush_network();
+
+    let inputs = &env["server"].server().players[0].inputs;
+    assert!(!inputs.contains_key(&NetworkTypeName::of::<TestGameEvent>()));
+}
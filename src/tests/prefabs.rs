@@ -0,0 +1,128 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// a prefab registered after TestPrefab
+#[derive(Component, Clone, Serialize, Deserialize)]
+struct OtherPrefab;
+
+fn get_prefab(app: &mut App, network_entity: NetworkEntity) -> Option<(TestPrefab, TestComponent)> {
+    let mut query = app
+        .world
+        .query::<(&NetworkEntity, Option<&TestPrefab>, Option<&TestComponent>)>();
+    let (_, prefab, component) = query
+        .iter(&app.world)
+        .find(|(e, _, _)| **e == network_entity)
+        .unwrap();
+    prefab.cloned().zip(component.cloned())
+}
+
+#[test]
+fn spawn_with_prefab() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestPrefab { value: 5 });
+    let plain_network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(plain_network_entity);
+    env.flush_network();
+
+    assert_eq!(
+        get_prefab(env["client1"].app(), network_entity),
+        Some((TestPrefab { value: 5 }, TestComponent(5)))
+    );
+    assert_eq!(get_prefab(env["client1"].app(), plain_network_entity), None);
+}
+
+#[test]
+fn spawn_with_prefab_on_join() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestPrefab { value: 7 });
+    env.flush_network();
+
+    env.create_client("client1", "server");
+    env.flush_network();
+
+    assert_eq!(
+        get_prefab(env["client1"].app(), network_entity),
+        Some((TestPrefab { value: 7 }, TestComponent(7)))
+    );
+}
+
+#[test]
+fn spawn_with_prefab_when_relevant() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestPrefab { value: 3 });
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client1_me, false);
+    env.flush_network();
+
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client1_me, true);
+    env.flush_network();
+
+    assert_eq!(
+        get_prefab(env["client1"].app(), network_entity),
+        Some((TestPrefab { value: 3 }, TestComponent(3)))
+    );
+}
+
+#[test]
+fn spawn_with_first_registered_prefab() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    for name in ["server", "client1"] {
+        env[name].app().add_network_prefab(|_, _: &OtherPrefab| {});
+    }
+    env.flush_network();
+
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(OtherPrefab)
+        .insert(TestPrefab { value: 5 });
+    env.flush_network();
+
+    assert_eq!(
+        get_prefab(env["client1"].app(), network_entity),
+        Some((TestPrefab { value: 5 }, TestComponent(5)))
+    );
+    let client_entity = find_entity(env["client1"].app(), network_entity).unwrap();
+    assert!(env["client1"]
+        .world()
+        .get::<OtherPrefab>(client_entity)
+        .is_none());
+}
@@ -0,0 +1,125 @@
+use super::common::prelude::*;
+use crate::{prelude::*, server::PENDING_TIMEOUT_TICKS};
+
+#[test]
+fn spawn_cycles_stay_bounded() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_two_clients(&mut env);
+
+    let kept = NetworkEntity::new();
+    env["server"].world().spawn().insert(kept);
+    env.flush_network();
+    let baseline = env["server"].server().relevancy_entry_count();
+    assert!(baseline > 0);
+
+    for _ in 0..2000 {
+        let entity = env["server"]
+            .world()
+            .spawn()
+            .insert(NetworkEntity::new())
+            .id();
+        env["server"].app().update();
+        assert!(env["server"].server().relevancy_entry_count() <= baseline * 2);
+        env["server"].world().despawn(entity);
+        env["server"].app().update();
+        assert_eq!(env["server"].server().relevancy_entry_count(), baseline);
+    }
+    env.flush_network();
+
+    assert_eq!(env["server"].server().relevancy_entry_count(), baseline);
+    assert!(has_entity(&mut env, "client1", kept));
+    let mut query = env["client1"].world().query::<&NetworkEntity>();
+    assert_eq!(query.iter(env["client1"].world()).count(), 1);
+}
+
+#[test]
+fn player_leave_purges_entries() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_two_clients(&mut env);
+
+    for _ in 0..10 {
+        env["server"].world().spawn().insert(NetworkEntity::new());
+    }
+    env.flush_network();
+    let before = env["server"].server().relevancy_entry_count();
+
+    env["client2"].network().stop();
+    env.flush_network();
+
+    assert_eq!(env["server"].server().relevancy_entry_count(), before - 10);
+}
+
+#[test]
+fn unknown_entries_dropped() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    let baseline = env["server"].server().relevancy_entry_count();
+
+    env["client2"].network().stop();
+    env.flush_network();
+    let after_leave = env["server"].server().relevancy_entry_count();
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client2_me, true);
+    env["server"]
+        .server()
+        .set_player_viewpoint(client2_me, Some(network_entity));
+    env.flush_network();
+    assert_eq!(env["server"].server().relevancy_entry_count(), after_leave);
+    assert!(after_leave < baseline);
+
+    env["server"]
+        .server()
+        .set_entity_relevant(NetworkEntity::new(), client1_me, true);
+    for _ in 0..PENDING_TIMEOUT_TICKS + 10 {
+        env["server"].app().update();
+    }
+    assert_eq!(env["server"].server().relevancy_entry_count(), after_leave);
+}
+
+#[test]
+fn unchanged_entities_keep_relevancy() {
+    let mut env = TestEnvironment::default();
+    let (_, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    env.flush_network();
+    assert!(has_entity(&mut env, "client2", network_entity));
+    assert_eq!(env["client2"].introspect().entity_spawn_events.len(), 1);
+
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client2_me, false);
+    env.flush_network();
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client2", network_entity));
+    assert_eq!(env["client2"].introspect().entity_despawn_events.len(), 1);
+
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client2_me, true);
+    env.flush_network();
+    assert!(has_entity(&mut env, "client2", network_entity));
+    assert_eq!(env["client2"].introspect().entity_spawn_events.len(), 2);
+}
+
+#[test]
+fn late_joiner_gets_existing_entities() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    env.create_client("client3", "server");
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client3", network_entity));
+}
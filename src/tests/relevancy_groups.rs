@@ -0,0 +1,108 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+
+#[test]
+fn shared_group() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    let dungeon = NetworkEntity::new();
+    let lobby = NetworkEntity::new();
+    let ungrouped = NetworkEntity::new();
+    let server = env["server"].server();
+    server.add_player_to_group(client1_me, "dungeon");
+    server.add_player_to_group(client2_me, "lobby");
+    server.add_entity_to_group(dungeon, "dungeon");
+    server.add_entity_to_group(lobby, "lobby");
+    for network_entity in [dungeon, lobby, ungrouped] {
+        env["server"].world().spawn().insert(network_entity);
+    }
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", dungeon));
+    assert!(!has_entity(&mut env, "client1", lobby));
+    assert!(has_entity(&mut env, "client1", ungrouped));
+    assert!(!has_entity(&mut env, "client2", dungeon));
+    assert!(has_entity(&mut env, "client2", lobby));
+    assert!(has_entity(&mut env, "client2", ungrouped));
+}
+
+#[test]
+fn player_membership_changes() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, _) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .server()
+        .add_entity_to_group(network_entity, "team");
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client1", network_entity));
+
+    env["server"]
+        .server()
+        .add_player_to_group(client1_me, "team");
+    env.flush_network();
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+
+    env["server"]
+        .server()
+        .remove_player_from_group(client1_me, "team");
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client1", network_entity));
+    assert_eq!(
+        env["client1"]
+            .introspect()
+            .entity_despawn_events
+            .last()
+            .unwrap()
+            .reason,
+        NetworkEntityDespawnReason::Irrelevant
+    );
+}
+
+#[test]
+fn entity_membership_changes() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, _) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    env["server"].world().spawn().insert(network_entity);
+    env["server"]
+        .server()
+        .add_player_to_group(client1_me, "red");
+    env.flush_network();
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(has_entity(&mut env, "client2", network_entity));
+
+    env["server"]
+        .server()
+        .add_entity_to_group(network_entity, "red");
+    env.flush_network();
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+
+    env["server"]
+        .server()
+        .remove_entity_from_group(network_entity, "red");
+    env.flush_network();
+    assert!(has_entity(&mut env, "client2", network_entity));
+}
+
+#[test]
+fn owner_always_relevant() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, _) = setup_server_and_two_clients(&mut env);
+
+    let network_entity = NetworkEntity::new();
+    let server = env["server"].server();
+    server.add_entity_to_group(network_entity, "hidden");
+    server.set_entity_owner(network_entity, Some(client1_me));
+    env["server"].world().spawn().insert(network_entity);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+}
@@ -0,0 +1,115 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+#[test]
+fn and_rule() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_two_clients(&mut env);
+
+    // entities holding a zero are hidden from everyone
+    env["server"].app().add_network_relevancy_rule(
+        NetworkRelevancyRuleMode::And,
+        |world: &World, _: NetworkPlayer, entity: Entity| {
+            world
+                .get::<TestComponent>(entity)
+                .map_or(true, |component| component.0 != 0)
+        },
+    );
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestComponent(0))
+        .id();
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+
+    env["server"]
+        .world()
+        .get_mut::<TestComponent>(entity)
+        .unwrap()
+        .0 = 1;
+    env["server"]
+        .server()
+        .refresh_entity_relevancy(network_entity);
+    env.flush_network();
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(has_entity(&mut env, "client2", network_entity));
+}
+
+#[test]
+fn or_rule_overrides_manual() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    env["server"].app().add_network_relevancy_rule(
+        NetworkRelevancyRuleMode::Or,
+        move |world: &World, player: NetworkPlayer, entity: Entity| {
+            player == client1_me && world.get::<TestComponent>(entity).is_some()
+        },
+    );
+    let network_entity = NetworkEntity::new();
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(TestComponent(0));
+    let server = env["server"].server();
+    server.set_entity_relevant(network_entity, client1_me, false);
+    server.set_entity_relevant(network_entity, client2_me, false);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", network_entity));
+    assert!(!has_entity(&mut env, "client2", network_entity));
+}
+
+// visible to teammates or within range
+#[test]
+fn or_rule_with_spatial_interest() {
+    let mut env = TestEnvironment::default();
+    let (client1_me, client2_me) = setup_server_and_two_clients(&mut env);
+
+    env["server"].app().add_network_relevancy_rule(
+        NetworkRelevancyRuleMode::Or,
+        move |world: &World, player: NetworkPlayer, entity: Entity| {
+            let team = if player == client1_me { 1 } else { 2 };
+            world
+                .get::<TestComponent>(entity)
+                .map_or(false, |component| component.0 == team)
+        },
+    );
+    let spawn = |env: &mut TestEnvironment, x: f32, team: Option<u32>| {
+        let network_entity = NetworkEntity::new();
+        let mut entity = env["server"].world().spawn();
+        entity
+            .insert(network_entity)
+            .insert(Transform::from_xyz(x, 0., 0.))
+            .insert(GlobalTransform::default());
+        if let Some(team) = team {
+            entity.insert(TestComponent(team));
+        }
+        network_entity
+    };
+    let viewpoint1 = spawn(&mut env, 0., None);
+    let viewpoint2 = spawn(&mut env, 1000., None);
+    let teammate_far = spawn(&mut env, 100., Some(1));
+    let enemy_far = spawn(&mut env, 100., Some(2));
+    let neutral_near = spawn(&mut env, 5., None);
+    let server = env["server"].server();
+    server.set_entity_owner(viewpoint1, Some(client1_me));
+    server.set_entity_owner(viewpoint2, Some(client2_me));
+    server.set_player_viewpoint(client1_me, Some(viewpoint1));
+    server.set_player_viewpoint(client2_me, Some(viewpoint2));
+    server.set_spatial_interest(Some(NetworkSpatialInterest::new(50.)));
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client1", teammate_far));
+    assert!(!has_entity(&mut env, "client1", enemy_far));
+    assert!(has_entity(&mut env, "client1", neutral_near));
+    assert!(!has_entity(&mut env, "client2", teammate_far));
+    assert!(has_entity(&mut env, "client2", enemy_far));
+    assert!(!has_entity(&mut env, "client2", neutral_near));
+}
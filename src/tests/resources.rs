@@ -0,0 +1,218 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use serde::{Deserialize, Serialize, Serializer};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+// counts how often the server serializes it
+#[derive(Default, Deserialize)]
+#[serde(from = "u32")]
+struct CountedScore {
+    value: u32,
+    serialized: Arc<AtomicUsize>,
+}
+
+impl From<u32> for CountedScore {
+    fn from(value: u32) -> Self {
+        Self {
+            value,
+            ..Default::default()
+        }
+    }
+}
+
+impl Serialize for CountedScore {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.serialized.fetch_add(1, Ordering::Relaxed);
+        self.value.serialize(serializer)
+    }
+}
+
+#[test]
+fn sent_on_join() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env["server"]
+        .world()
+        .insert_resource(TestScore { value: 3 });
+    env.flush_network();
+    env.create_client("client", "server");
+    env.flush_network();
+
+    assert_eq!(
+        env["client"].world().get_resource::<TestScore>(),
+        Some(&TestScore { value: 3 })
+    );
+}
+
+#[test]
+fn updates() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+    assert!(env["client"].world().get_resource::<TestScore>().is_none());
+
+    env["server"]
+        .world()
+        .insert_resource(TestScore { value: 1 });
+    env.flush_network();
+    assert_eq!(
+        env["client"].world().get_resource::<TestScore>(),
+        Some(&TestScore { value: 1 })
+    );
+
+    env["server"]
+        .world()
+        .get_resource_mut::<TestScore>()
+        .unwrap()
+        .value = 2;
+    env.flush_network();
+    assert_eq!(
+        env["client"].world().get_resource::<TestScore>(),
+        Some(&TestScore { value: 2 })
+    );
+}
+
+#[test]
+fn removed() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env["server"]
+        .world()
+        .insert_resource(TestScore { value: 1 });
+    env.flush_network();
+    assert!(env["client"].world().get_resource::<TestScore>().is_some());
+
+    env["server"].world().remove_resource::<TestScore>();
+    env.flush_network();
+    assert!(env["client"].world().get_resource::<TestScore>().is_none());
+}
+
+#[test]
+fn filtered() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env.create_client("client2", "server");
+    env.flush_network();
+
+    let client1_me = env["client1"].network().me().unwrap();
+    let client2_me = env["client2"].network().me().unwrap();
+    env["server"].world().insert_resource(TestSecret {
+        player: client1_me,
+        value: 7,
+    });
+    env.flush_network();
+    assert_eq!(
+        env["client1"].world().get_resource::<TestSecret>(),
+        Some(&TestSecret {
+            player: client1_me,
+            value: 7
+        })
+    );
+    assert!(env["client2"]
+        .world()
+        .get_resource::<TestSecret>()
+        .is_none());
+
+    env["server"]
+        .world()
+        .get_resource_mut::<TestSecret>()
+        .unwrap()
+        .player = client2_me;
+    env.flush_network();
+    assert!(env["client1"]
+        .world()
+        .get_resource::<TestSecret>()
+        .is_none());
+    assert_eq!(
+        env["client2"].world().get_resource::<TestSecret>(),
+        Some(&TestSecret {
+            player: client2_me,
+            value: 7
+        })
+    );
+}
+
+#[test]
+fn server_client_keeps_own_resource() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server_client("server");
+    env.create_client("client", "server");
+    env["server"]
+        .world()
+        .insert_resource(TestScore { value: 5 });
+    env.flush_network();
+
+    assert_eq!(
+        env["server"].world().get_resource::<TestScore>(),
+        Some(&TestScore { value: 5 })
+    );
+    assert_eq!(
+        env["client"].world().get_resource::<TestScore>(),
+        Some(&TestScore { value: 5 })
+    );
+}
+
+#[test]
+fn serialized_only_when_changed() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client1", "server");
+    env["server"].app().add_network_resource::<CountedScore>();
+    env["client1"].app().add_network_resource::<CountedScore>();
+    let serialized = Arc::new(AtomicUsize::new(0));
+    env["server"].world().insert_resource(CountedScore {
+        value: 1,
+        serialized: serialized.clone(),
+    });
+    env.flush_network();
+    let after_insert = serialized.load(Ordering::Relaxed);
+    assert!(after_insert > 0);
+
+    env.flush_network();
+    assert_eq!(serialized.load(Ordering::Relaxed), after_insert);
+
+    // players that join later still get the unchanged resource
+    env.create_client("client2", "server");
+    env["client2"].app().add_network_resource::<CountedScore>();
+    env.flush_network();
+    assert_eq!(
+        env["client2"]
+            .world()
+            .get_resource::<CountedScore>()
+            .unwrap()
+            .value,
+        1
+    );
+
+    let before_change = serialized.load(Ordering::Relaxed);
+    env["server"]
+        .world()
+        .get_resource_mut::<CountedScore>()
+        .unwrap()
+        .value = 2;
+    env.flush_network();
+    assert!(serialized.load(Ordering::Relaxed) > before_change);
+    assert_eq!(
+        env["client1"]
+            .world()
+            .get_resource::<CountedScore>()
+            .unwrap()
+            .value,
+        2
+    );
+}
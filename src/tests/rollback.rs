@@ -0,0 +1,276 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+fn sum_inputs(inputs: Res<NetworkRollbackInputs<TestInput>>, mut state: ResMut<TestRollbackState>) {
+    for (_, input) in inputs.iter() {
+        state.sum += input.data.value;
+    }
+}
+
+static NONDETERMINISTIC_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+fn sum_nondeterministic(mut state: ResMut<TestRollbackState>) {
+    state.sum += NONDETERMINISTIC_COUNTER.fetch_add(1, Ordering::Relaxed);
+}
+
+fn rollback_sum(env: &mut TestEnvironment, app: &str) -> u32 {
+    env[app]
+        .world()
+        .get_resource::<TestRollbackState>()
+        .unwrap()
+        .sum
+}
+
+#[test]
+fn rollback_sync_test() {
+    let mut env = TestEnvironment::default();
+
+    env.create_app("local");
+    env["local"].app().add_network_rollback_system(sum_inputs);
+    env.start_local("local");
+    env.flush_network();
+
+    env["local"].client().set_rollback_sync_test(Some(4));
+    for value in 1..=10 {
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput { value });
+        env["local"].app().update();
+    }
+    env.flush_network();
+
+    assert_eq!(env["local"].client().rollback_tick(), Some(12));
+    assert_eq!(rollback_sum(&mut env, "local"), 55);
+    assert!(env["local"]
+        .introspect()
+        .sync_test_mismatch_events
+        .is_empty());
+}
+
+#[test]
+fn rollback_sync_test_mismatch() {
+    let mut env = TestEnvironment::default();
+
+    env.create_app("local");
+    env["local"]
+        .app()
+        .add_network_rollback_system(sum_nondeterministic);
+    env.start_local("local");
+    env.flush_network();
+
+    env["local"].client().set_rollback_sync_test(Some(2));
+    for _ in 0..5 {
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput::default());
+        env["local"].app().update();
+    }
+
+    assert!(!env["local"]
+        .introspect()
+        .sync_test_mismatch_events
+        .is_empty());
+}
+
+#[test]
+fn rollback_input_delay() {
+    let mut env = TestEnvironment::default();
+
+    env.create_local("local");
+    env.flush_network();
+
+    env["local"].client().set_rollback_input_delay(4);
+    assert_eq!(
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput { value: 1 }),
+        4
+    );
+    assert_eq!(
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput { value: 2 }),
+        5
+    );
+    env.flush_network();
+
+    assert_eq!(env["local"].client().rollback_tick(), Some(6));
+}
+
+#[test]
+fn rollback_corrects_mispredictions() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_app("client1");
+    env["client1"].app().add_network_rollback_system(sum_inputs);
+    env.start_client("client1", "server");
+    env.create_app("client2");
+    env["client2"].app().add_network_rollback_system(sum_inputs);
+    env.start_client("client2", "server");
+    env.flush_network();
+
+    // client1 runs ahead, predicting that client2 isn't pressing anything
+    for _ in 0..5 {
+        env["client1"]
+            .client()
+            .send_rollback_input(TestInput { value: 1 });
+        env["client1"].app().update();
+    }
+    assert_eq!(env["client1"].client().rollback_tick(), Some(7));
+    assert_eq!(rollback_sum(&mut env, "client1"), 5);
+
+    for _ in 0..5 {
+        env["client2"]
+            .client()
+            .send_rollback_input(TestInput { value: 2 });
+    }
+    env.flush_network();
+
+    assert_eq!(env["client1"].client().rollback_tick(), Some(7));
+    assert_eq!(env["client2"].client().rollback_tick(), Some(7));
+    assert_eq!(rollback_sum(&mut env, "client1"), 15);
+    assert_eq!(rollback_sum(&mut env, "client2"), 15);
+}
+
+fn start_rollback_client(env: &mut TestEnvironment, name: &str) {
+    env.create_app(name);
+    env[name].app().add_network_rollback_system(sum_inputs);
+    env.start_client(name, "server");
+}
+
+#[test]
+fn rollback_late_join() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    start_rollback_client(&mut env, "client1");
+    start_rollback_client(&mut env, "client2");
+    env.flush_network();
+
+    for _ in 0..30 {
+        for app in ["client1", "client2"] {
+            env[app]
+                .client()
+                .send_rollback_input(TestInput { value: 1 });
+        }
+        env.flush_network();
+    }
+    assert_eq!(env["client1"].client().rollback_tick(), Some(32));
+
+    // the late client starts at the session's tick instead of 0, so nobody waits on its old ticks
+    start_rollback_client(&mut env, "client3");
+    env.flush_network();
+    let start_tick = env["client3"].client().rollback_tick().unwrap();
+    assert!(start_tick >= 30);
+
+    for _ in 0..30 {
+        for app in ["client1", "client2", "client3"] {
+            env[app]
+                .client()
+                .send_rollback_input(TestInput { value: 1 });
+        }
+        env.flush_network();
+    }
+    assert_eq!(env["client1"].client().rollback_tick(), Some(62));
+    assert_eq!(env["client2"].client().rollback_tick(), Some(62));
+    assert_eq!(
+        env["client3"].client().rollback_tick(),
+        Some(start_tick + 32)
+    );
+}
+
+#[test]
+fn rollback_ignores_unknown_input_types() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    start_rollback_client(&mut env, "client1");
+    start_rollback_client(&mut env, "client2");
+    env.flush_network();
+
+    env["client2"]
+        .client()
+        .send_rollback_input(TestPlayerData::default());
+    for _ in 0..3 {
+        env["client1"]
+            .client()
+            .send_rollback_input(TestInput { value: 1 });
+    }
+    env.flush_network();
+
+    assert_eq!(env["client1"].client().rollback_tick(), Some(5));
+    assert_eq!(rollback_sum(&mut env, "client1"), 3);
+}
+
+#[test]
+#[should_panic(expected = "another input type already is.")]
+fn rollback_single_input_type() {
+    let mut app = App::new();
+    app.setup_for_tests()
+        .add_network_rollback::<TestPlayerData>();
+}
+
+#[test]
+fn rollback_sync_test_clamped() {
+    let mut env = TestEnvironment::default();
+
+    env.create_app("local");
+    env["local"]
+        .app()
+        .add_network_rollback_system(sum_nondeterministic);
+    env.start_local("local");
+    env.flush_network();
+
+    // more ticks than snapshots are kept for still checks the oldest one
+    env["local"].client().set_rollback_sync_test(Some(100));
+    for _ in 0..40 {
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput::default());
+        env["local"].app().update();
+    }
+
+    assert!(!env["local"]
+        .introspect()
+        .sync_test_mismatch_events
+        .is_empty());
+}
+
+#[derive(Component, Clone, Serialize)]
+struct Spawned;
+
+fn spawn_on_tick_3(mut commands: Commands, inputs: Res<NetworkRollbackInputs<TestInput>>) {
+    if inputs.tick() == 3 {
+        commands.spawn().insert(Spawned);
+    }
+}
+
+#[test]
+fn rollback_past_spawn_desyncs() {
+    let mut env = TestEnvironment::default();
+
+    env.create_app("local");
+    env["local"]
+        .app()
+        .add_network_rollback_component::<Spawned>()
+        .add_network_rollback_system(spawn_on_tick_3);
+    env.start_local("local");
+    env.flush_network();
+
+    env["local"].client().set_rollback_sync_test(Some(2));
+    for _ in 0..6 {
+        env["local"]
+            .client()
+            .send_rollback_input(TestInput::default());
+        env["local"].app().update();
+    }
+
+    // the spawned entity isn't spawned again by resimulating
+    let mut query = env["local"].world().query::<&Spawned>();
+    assert_eq!(query.iter(env["local"].world()).count(), 1);
+    assert!(!env["local"].introspect().desync_events.is_empty());
+}
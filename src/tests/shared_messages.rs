@@ -0,0 +1,130 @@
+use super::common::prelude::*;
+use crate::{
+    network::{send_deferred_events, server_entities_diff, server_send_entity_events},
+    prelude::*,
+    server::NetworkServerPlayer,
+};
+use bevy::prelude::*;
+use bevy_nety_protocol::NetworkSocketProtocol;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+type Sent = Arc<Mutex<Vec<Arc<str>>>>;
+
+// keeps every message it's given, so tests can check whether players share one encoding
+struct RecordingSocket {
+    sent: Sent,
+}
+
+impl NetworkSocketProtocol for RecordingSocket {
+    fn update(&mut self) {}
+    fn connected(&mut self) -> bool {
+        true
+    }
+    fn send(&mut self, message: String) {
+        self.sent.lock().unwrap().push(message.into());
+    }
+    fn send_shared(&mut self, message: Arc<str>) {
+        self.sent.lock().unwrap().push(message);
+    }
+    fn receive(&mut self) -> Option<String> {
+        None
+    }
+    fn disconnect(&mut self) {}
+}
+
+fn setup(players: usize) -> (Network, Vec<(NetworkPlayer, Sent)>) {
+    let mut network = Network::default();
+    network.start_server(vec![]);
+    let server = network.server_mut().unwrap();
+    let mut sockets = vec![];
+    for _ in 0..players {
+        let handle = NetworkPlayer::new();
+        let sent = Sent::default();
+        server.players.push(NetworkServerPlayer {
+            initialized: true,
+            handle,
+            socket: Box::new(RecordingSocket { sent: sent.clone() }),
+            data: Default::default(),
+            rtt: 0.,
+            interpolation_delay: 0.,
+            inputs: HashMap::new(),
+            resources: HashMap::new(),
+            resources_sent: false,
+            bandwidth: Default::default(),
+        });
+        sockets.push((handle, sent));
+    }
+    (network, sockets)
+}
+
+fn last_messages(sockets: &[(NetworkPlayer, Sent)]) -> Vec<Arc<str>> {
+    sockets
+        .iter()
+        .filter_map(|(_, sent)| sent.lock().unwrap().last().cloned())
+        .collect()
+}
+
+fn all_shared(messages: &[Arc<str>]) -> bool {
+    messages
+        .windows(2)
+        .all(|pair| Arc::ptr_eq(&pair[0], &pair[1]))
+}
+
+#[test]
+fn send_to_all() {
+    let (mut network, sockets) = setup(4);
+
+    network
+        .server_mut()
+        .unwrap()
+        .send_to_all(TestGameEvent { foo: "bar".into() });
+    send_deferred_events(&mut network, &mut World::new());
+
+    let messages = last_messages(&sockets);
+    assert_eq!(messages.len(), 4);
+    assert!(all_shared(&messages));
+}
+
+#[test]
+fn send_to_players() {
+    let (mut network, sockets) = setup(4);
+
+    let players = vec![sockets[1].0, sockets[3].0];
+    network
+        .server_mut()
+        .unwrap()
+        .send_to_players(&players, TestGameEvent { foo: "bar".into() });
+    send_deferred_events(&mut network, &mut World::new());
+
+    assert!(sockets[0].1.lock().unwrap().is_empty());
+    assert!(sockets[2].1.lock().unwrap().is_empty());
+    let messages = last_messages(&sockets);
+    assert_eq!(messages.len(), 2);
+    assert!(all_shared(&messages));
+}
+
+#[test]
+fn entity_spawns_and_events() {
+    let (mut network, sockets) = setup(4);
+    let mut world = World::new();
+
+    let network_entity = NetworkEntity::new();
+    world.spawn().insert(network_entity);
+    server_entities_diff(&mut network, &mut world);
+    let messages = last_messages(&sockets);
+    assert_eq!(messages.len(), 4);
+    assert!(all_shared(&messages));
+
+    network
+        .server_mut()
+        .unwrap()
+        .send_to_entity(network_entity, TestGameEvent { foo: "bar".into() });
+    send_deferred_events(&mut network, &mut world);
+    server_send_entity_events(&mut network);
+    let events = last_messages(&sockets);
+    assert!(all_shared(&events));
+    assert!(!Arc::ptr_eq(&messages[0], &events[0]));
+}
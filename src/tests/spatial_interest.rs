@@ -0,0 +1,161 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+fn spawn_at(env: &mut TestEnvironment, x: f32) -> (NetworkEntity, Entity) {
+    let network_entity = NetworkEntity::new();
+    let entity = env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(Transform::from_xyz(x, 0., 0.))
+        .insert(GlobalTransform::default())
+        .id();
+    (network_entity, entity)
+}
+
+fn setup(env: &mut TestEnvironment) -> NetworkEntity {
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    let (viewpoint, _) = spawn_at(env, 0.);
+    let server = env["server"].server();
+    server.set_entity_owner(viewpoint, Some(client_me));
+    server.set_player_viewpoint(client_me, Some(viewpoint));
+    server.set_spatial_interest(Some(NetworkSpatialInterest {
+        radius: 10.,
+        hysteresis: 2.,
+    }));
+    viewpoint
+}
+
+#[test]
+fn within_radius() {
+    let mut env = TestEnvironment::default();
+    let viewpoint = setup(&mut env);
+
+    let (near, _) = spawn_at(&mut env, 5.);
+    let (far, _) = spawn_at(&mut env, 50.);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client", viewpoint));
+    assert!(has_entity(&mut env, "client", near));
+    assert!(!has_entity(&mut env, "client", far));
+}
+
+#[test]
+fn enter_and_leave() {
+    let mut env = TestEnvironment::default();
+    setup(&mut env);
+
+    let (network_entity, entity) = spawn_at(&mut env, 20.);
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client", network_entity));
+
+    env["server"]
+        .world()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .x = 9.;
+    env.flush_network();
+    assert!(has_entity(&mut env, "client", network_entity));
+
+    // past the radius but within the hysteresis band
+    env["server"]
+        .world()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .x = 11.;
+    env.flush_network();
+    assert!(has_entity(&mut env, "client", network_entity));
+
+    env["server"]
+        .world()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .x = 13.;
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client", network_entity));
+    assert_eq!(
+        env["client"]
+            .introspect()
+            .entity_despawn_events
+            .last()
+            .unwrap()
+            .reason,
+        NetworkEntityDespawnReason::Irrelevant
+    );
+
+    // coming back in only happens inside the radius
+    env["server"]
+        .world()
+        .get_mut::<Transform>(entity)
+        .unwrap()
+        .translation
+        .x = 11.;
+    env.flush_network();
+    assert!(!has_entity(&mut env, "client", network_entity));
+}
+
+#[test]
+fn manual_relevancy_still_applies() {
+    let mut env = TestEnvironment::default();
+    setup(&mut env);
+
+    let (network_entity, _) = spawn_at(&mut env, 1.);
+    let client_me = env["client"].network().me().unwrap();
+    env["server"]
+        .server()
+        .set_entity_relevant(network_entity, client_me, false);
+    env.flush_network();
+
+    assert!(!has_entity(&mut env, "client", network_entity));
+}
+
+#[test]
+fn no_viewpoint() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env["server"]
+        .server()
+        .set_spatial_interest(Some(NetworkSpatialInterest::new(10.)));
+    env.flush_network();
+
+    let (far, _) = spawn_at(&mut env, 50.);
+    let unpositioned = NetworkEntity::new();
+    env["server"].world().spawn().insert(unpositioned);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client", far));
+    assert!(has_entity(&mut env, "client", unpositioned));
+}
+
+#[test]
+fn children_use_global_position() {
+    let mut env = TestEnvironment::default();
+    setup(&mut env);
+
+    let parent = env["server"]
+        .world()
+        .spawn()
+        .insert(Transform::from_xyz(100., 0., 0.))
+        .insert(GlobalTransform::default())
+        .id();
+    let (near, near_entity) = spawn_at(&mut env, -95.);
+    let (far, far_entity) = spawn_at(&mut env, 5.);
+    env["server"]
+        .world()
+        .entity_mut(parent)
+        .push_children(&[near_entity, far_entity]);
+    env.flush_network();
+
+    assert!(has_entity(&mut env, "client", near));
+    assert!(!has_entity(&mut env, "client", far));
+}
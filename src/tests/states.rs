@@ -0,0 +1,134 @@
+use super::common::prelude::*;
+use bevy::prelude::*;
+
+fn current_state(env: &mut TestEnvironment, name: &str) -> TestGameState {
+    *env[name]
+        .world()
+        .get_resource::<State<TestGameState>>()
+        .unwrap()
+        .current()
+}
+
+fn set_state(env: &mut TestEnvironment, name: &str, state: TestGameState) {
+    env[name]
+        .world()
+        .get_resource_mut::<State<TestGameState>>()
+        .unwrap()
+        .set(state)
+        .unwrap();
+}
+
+#[test]
+fn server_transitions() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+    assert_eq!(current_state(&mut env, "client"), TestGameState::Lobby);
+
+    set_state(&mut env, "server", TestGameState::InGame);
+    env.flush_network();
+    assert_eq!(current_state(&mut env, "client"), TestGameState::InGame);
+
+    set_state(&mut env, "server", TestGameState::Results);
+    env.flush_network();
+    assert_eq!(current_state(&mut env, "client"), TestGameState::Results);
+}
+
+#[test]
+fn late_joiner() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    set_state(&mut env, "server", TestGameState::InGame);
+    env.flush_network();
+    env.create_client("client", "server");
+    env.flush_network();
+
+    assert_eq!(current_state(&mut env, "client"), TestGameState::InGame);
+}
+
+#[test]
+fn client_change_becomes_request() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    let client_me = env["client"].network().me().unwrap();
+    set_state(&mut env, "client", TestGameState::Results);
+    env.flush_network();
+
+    assert_eq!(current_state(&mut env, "client"), TestGameState::Lobby);
+    assert_eq!(current_state(&mut env, "server"), TestGameState::Lobby);
+    let introspect = env["server"].introspect();
+    assert_eq!(introspect.state_request_events.len(), 1);
+    assert_eq!(introspect.state_request_events[0].player, client_me);
+    assert_eq!(
+        introspect.state_request_events[0].state,
+        TestGameState::Results
+    );
+}
+
+#[test]
+fn server_state_wins_same_frame_change() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env.flush_network();
+
+    set_state(&mut env, "server", TestGameState::InGame);
+    set_state(&mut env, "client", TestGameState::Results);
+    env.flush_network();
+
+    assert_eq!(current_state(&mut env, "client"), TestGameState::InGame);
+    assert_eq!(current_state(&mut env, "server"), TestGameState::InGame);
+}
+
+#[derive(Default)]
+struct EnterCount(u32);
+
+fn count_enter(mut count: ResMut<EnterCount>) {
+    count.0 += 1;
+}
+
+#[test]
+fn client_change_never_entered() {
+    let mut env = TestEnvironment::default();
+
+    env.create_server("server");
+    env.create_client("client", "server");
+    env["client"]
+        .app()
+        .init_resource::<EnterCount>()
+        .add_system_set(State::on_enter_set(TestGameState::Results).with_system(count_enter));
+    env.flush_network();
+
+    set_state(&mut env, "client", TestGameState::Results);
+    env.flush_network();
+    assert_eq!(current_state(&mut env, "client"), TestGameState::Lobby);
+    assert_eq!(
+        env["client"]
+            .world()
+            .get_resource::<EnterCount>()
+            .unwrap()
+            .0,
+        0
+    );
+    assert_eq!(env["server"].introspect().state_request_events.len(), 1);
+
+    set_state(&mut env, "server", TestGameState::Results);
+    env.flush_network();
+    assert_eq!(current_state(&mut env, "client"), TestGameState::Results);
+    assert_eq!(
+        env["client"]
+            .world()
+            .get_resource::<EnterCount>()
+            .unwrap()
+            .0,
+        1
+    );
+}
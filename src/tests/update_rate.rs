@@ -0,0 +1,115 @@
+use super::common::prelude::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use uuid::Uuid;
+
+#[test]
+fn levels() {
+    let update_rate = NetworkUpdateRate::every(1)
+        .with_level(50., 16)
+        .with_level(10., 4);
+
+    assert_eq!(update_rate.interval(None), 1);
+    assert_eq!(update_rate.interval(Some(5.)), 1);
+    assert_eq!(update_rate.interval(Some(20.)), 4);
+    assert_eq!(update_rate.interval(Some(60.)), 16);
+    assert_eq!(NetworkUpdateRate::every(0).interval(None), 1);
+}
+
+#[test]
+fn updates_held_until_due() {
+    let mut env = TestEnvironment::default();
+    setup_server_and_client(&mut env);
+
+    // the handle offsets which ticks the entity is due on
+    let network_entity = NetworkEntity(Uuid::from_u128(0));
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(NetworkUpdateRate::every(5));
+    env.flush_network();
+    for i in 0..10 {
+        send_entity_event(&mut env, network_entity, &i.to_string());
+        env["server"].app().update();
+        let tick = env["server"].server().tick;
+        assert_eq!(
+            env["server"].server().players[0].bandwidth.has_pending(),
+            tick % 5 != 0
+        );
+    }
+    env.flush_network();
+
+    assert_eq!(
+        received_entity_events(&mut env),
+        (0..10).map(|i| i.to_string()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn spawn_and_ownership_immediate() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let network_entity = NetworkEntity(Uuid::from_u128(1));
+    env["server"]
+        .world()
+        .spawn()
+        .insert(network_entity)
+        .insert(NetworkUpdateRate::every(1000));
+    env.flush_network();
+    assert_eq!(env["client"].introspect().entity_spawn_events.len(), 1);
+
+    env["server"]
+        .server()
+        .set_entity_owner(network_entity, Some(client_me));
+    send_entity_event(&mut env, network_entity, "held");
+    env.flush_network();
+
+    assert_eq!(
+        env["client"].network().entity_owner(network_entity),
+        Some(client_me)
+    );
+    assert!(received_entity_events(&mut env).is_empty());
+}
+
+#[test]
+fn distant_entities_update_less() {
+    let mut env = TestEnvironment::default();
+    let client_me = setup_server_and_client(&mut env);
+
+    let viewpoint = NetworkEntity::new();
+    let near = NetworkEntity(Uuid::from_u128(1));
+    let far = NetworkEntity(Uuid::from_u128(2));
+    let update_rate = NetworkUpdateRate::every(1).with_level(50., 1000);
+    env["server"]
+        .world()
+        .spawn()
+        .insert(viewpoint)
+        .insert(Transform::default())
+        .insert(GlobalTransform::default());
+    env["server"]
+        .world()
+        .spawn()
+        .insert(near)
+        .insert(Transform::from_xyz(10., 0., 0.))
+        .insert(GlobalTransform::default())
+        .insert(update_rate.clone());
+    env["server"]
+        .world()
+        .spawn()
+        .insert(far)
+        .insert(Transform::from_xyz(100., 0., 0.))
+        .insert(GlobalTransform::default())
+        .insert(update_rate);
+    env["server"]
+        .server()
+        .set_player_viewpoint(client_me, Some(viewpoint));
+    env.flush_network();
+
+    send_entity_event(&mut env, near, "near");
+    send_entity_event(&mut env, far, "far");
+    env.flush_network();
+
+    assert_eq!(received_entity_events(&mut env), vec!["near"]);
+}
@@ -0,0 +1,49 @@
+use crate::entity::NetworkEntity;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+// how often the server sends the updates of an entity, they're held until the entity is due,
+// spawns, despawns and ownership changes are always sent right away
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct NetworkUpdateRate {
+    // updates are sent every this many ticks
+    pub interval: u64,
+    // players at least this far away get updates every this many ticks instead, needs a viewpoint
+    // for the player and a GlobalTransform on the entity
+    pub levels: Vec<(f32, u64)>,
+}
+
+impl NetworkUpdateRate {
+    pub fn every(interval: u64) -> Self {
+        Self {
+            interval,
+            levels: vec![],
+        }
+    }
+
+    pub fn with_level(mut self, distance: f32, interval: u64) -> Self {
+        self.levels.push((distance, interval));
+        self.levels
+            .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        self
+    }
+
+    pub(crate) fn interval(&self, distance: Option<f32>) -> u64 {
+        let interval = distance
+            .and_then(|distance| {
+                self.levels
+                    .iter()
+                    .rev()
+                    .find(|(level_distance, _)| distance >= *level_distance)
+            })
+            .map(|(_, interval)| *interval)
+            .unwrap_or(self.interval);
+        interval.max(1)
+    }
+
+    // entities are offset by their handle, so entities with the same rate don't all send on one tick
+    pub(crate) fn due(&self, entity: NetworkEntity, tick: u64, distance: Option<f32>) -> bool {
+        let offset = entity.0.as_u128() as u64;
+        tick.wrapping_add(offset) % self.interval(distance) == 0
+    }
+}